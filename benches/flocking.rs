@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+use criterion::{ BenchmarkId, Criterion, black_box, criterion_group, criterion_main };
+use rand::Rng;
+
+use bevy_test::{ util::*, bidimensional::*, headless::* };
+
+/// Members per 100x100 area, the world is sized to fit every member at this density.
+const DENSITIES: &[f32] = &[1.0, 4.0];
+
+fn params() -> FlockMemberParams {
+    FlockMemberParams {
+        max_speed: 200.0,
+        max_accel: 100.0,
+        safe_radius: 15.0
+    }
+}
+
+/// The headless simulation with `flocks` flocks of `members` members each, scattered over the whole world.
+fn headless_simulation(flocks: usize, members: usize, density: f32, batch_size: Option<usize>) -> HeadlessSimulation {
+    let side = ((flocks * members) as f32 / density).sqrt() * 100.0;
+    let batch_size = FlockingBatchSize(batch_size.unwrap_or(FlockingBatchSize::default().0));
+
+    let mut simulation = HeadlessSimulation::with_plugins(side, side, |app| {
+        app.add_resource(batch_size);
+    });
+    let mut rng = SimRng::new(0).stream("bench_flocks");
+
+    for _ in 0..flocks {
+        let positions: Vec<Vec2> = (0..members)
+            .map(|_| Vec2::new(rng.gen_range(-side / 2.0..side / 2.0), rng.gen_range(-side / 2.0..side / 2.0)))
+            .collect();
+
+        simulation.spawn_flock(&FlockDescription::at(&positions).with_params(params()));
+    }
+
+    // The first tick inserts `FlockStats`
+    simulation.step(1);
+    simulation
+}
+
+fn tick(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick");
+    group.sample_size(20);
+
+    for &(flocks, members) in &[(1, 500), (1, 2000), (4, 500), (4, 2000)] {
+        for &density in DENSITIES {
+            let mut simulation = headless_simulation(flocks, members, density, None);
+            group.bench_with_input(BenchmarkId::new(format!("{}x{}", flocks, members), density), &density, |b, _| b.iter(|| simulation.step(1)));
+        }
+    }
+
+    group.finish();
+}
+
+fn parallel(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parallel");
+    group.sample_size(10);
+
+    for &members in &[5_000, 20_000, 50_000] {
+        for &(name, batch_size) in &[("serial", usize::MAX), ("batched", FlockingBatchSize::default().0)] {
+            let mut simulation = headless_simulation(members / 1000, 1000, DENSITIES[0], Some(batch_size));
+            group.bench_with_input(BenchmarkId::new(name, members), &members, |b, _| b.iter(|| simulation.step(1)));
+        }
+    }
+
+    group.finish();
+}
+
+fn separation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("separation");
+    let mut rng = SimRng::new(0).stream("bench_separation");
+    let params = params();
+
+    for &count in &[100, 1000] {
+        let boids: Vec<(u32, Vec2, FlockMemberParams)> = (0..count as u32)
+            .map(|id| (id, Vec2::new(rng.gen_range(-500.0..500.0), rng.gen_range(-500.0..500.0)), params))
+            .collect();
+
+        let mut snapshot = FlockSnapshot::with_capacity(count);
+        for (id, position, params) in boids.iter() {
+            snapshot.push(*id, *position, Vec2::zero(), Vec2::zero(), params);
+        }
+        snapshot.set_averages(Vec2::zero(), Vec2::zero(), WorldBounds::fixed_size(1000.0, 1000.0).bounds);
+
+        group.bench_with_input(BenchmarkId::new("calculate_separation", count), &boids, |b, boids| {
+            b.iter(|| FlockingPlugin::calculate_separation(0, &params, black_box(boids[0].1), boids))
+        });
+
+        let mut out = vec![Vec2::zero(); 1];
+        group.bench_with_input(BenchmarkId::new("snapshot", count), &snapshot, |b, snapshot| {
+            b.iter(|| snapshot.separation(black_box(0..1), &mut out))
+        });
+    }
+
+    group.finish();
+}
+
+fn bound_to(c: &mut Criterion) {
+    let bounds = WorldBounds::fixed_size(1024.0, 800.0).bounds;
+    let points = [Vec2::new(0.0, 0.0), Vec2::new(600.0, -500.0), Vec2::new(-1500.0, 1200.0)];
+
+    c.bench_function("bound_to", |b| {
+        b.iter(|| {
+            for point in points.iter() {
+                black_box(black_box(*point).bound_to(Vec2::new(100.0, 100.0), bounds));
+            }
+        })
+    });
+}
+
+criterion_group!(benches, tick, parallel, separation, bound_to);
+criterion_main!(benches);
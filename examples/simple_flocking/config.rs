@@ -0,0 +1,216 @@
+use std::path::PathBuf;
+
+pub const USAGE: &'static str = "\
+Usage: simple_flocking [OPTIONS]
+
+Options:
+    --width <PIXELS>        Window width [default: 1024]
+    --height <PIXELS>       Window height [default: 800]
+    --vsync                 Enable vsync
+    --flocks <COUNT>        Number of flocks [default: 2]
+    --members <COUNT>       Members per flock [default: 99]
+    --seed <SEED>           Seed for all randomness, random when not given
+    --preset <ASSET>        Flock preset asset, also read by headless runs [default: presets/default.flock]
+    --headless <TICKS>      Run this many ticks without a window, then exit
+    --metrics <PATH>        Write flock metrics to this file
+    --help                  Print this message
+
+On the web the same options are read from the URL query, e.g. `?flocks=4&members=50&vsync`.";
+
+/// Example settings from the command line, or from the URL query under `web`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExampleConfig {
+    pub width: Option<f32>,
+    pub height: Option<f32>,
+    pub vsync: bool,
+    pub flocks: usize,
+    pub members: usize,
+    pub seed: Option<u64>,
+    pub preset: String,
+    #[cfg_attr(feature = "web", allow(dead_code))]
+    pub headless_ticks: Option<u64>,
+    pub metrics: Option<PathBuf>
+}
+
+impl Default for ExampleConfig {
+    fn default() -> Self {
+        ExampleConfig {
+            width: None,
+            height: None,
+            vsync: false,
+            flocks: 2,
+            members: 99,
+            seed: None,
+            preset: "presets/default.flock".to_string(),
+            headless_ticks: None,
+            metrics: None
+        }
+    }
+}
+
+impl ExampleConfig {
+    pub const DEFAULT_WIDTH: f32 = 1024.0;
+    pub const DEFAULT_HEIGHT: f32 = 800.0;
+
+    fn parse<T: std::str::FromStr>(name: &str, value: Option<String>) -> Result<T, String> {
+        let value = value.ok_or_else(|| format!("missing value for `{}`", name))?;
+        value.parse().map_err(|_| format!("invalid value `{}` for `{}`", value, name))
+    }
+
+    /// Builds the config from option names without their dashes and their values. `vsync` is the only flag,
+    /// it may be given without a value.
+    pub fn from_options(options: impl IntoIterator<Item = (String, Option<String>)>) -> Result<Self, String> {
+        let mut config = ExampleConfig::default();
+
+        for (name, value) in options {
+            match name.as_str() {
+                "width" => config.width = Some(Self::parse(&name, value)?),
+                "height" => config.height = Some(Self::parse(&name, value)?),
+                "vsync" => config.vsync = match value {
+                    Some(value) => Self::parse(&name, Some(value))?,
+                    None => true
+                },
+                "flocks" => config.flocks = Self::parse(&name, value)?,
+                "members" => config.members = Self::parse(&name, value)?,
+                "seed" => config.seed = Some(Self::parse(&name, value)?),
+                "preset" => config.preset = Self::parse(&name, value)?,
+                "headless" => config.headless_ticks = Some(Self::parse(&name, value)?),
+                "metrics" => config.metrics = Some(Self::parse(&name, value)?),
+                _ => return Err(format!("unknown option `{}`", name))
+            }
+        }
+
+        Ok(config)
+    }
+
+    /// Accepts both `--name value` and `--name=value`.
+    #[cfg(any(not(feature = "web"), test))]
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        let mut args = args.iter().cloned();
+        let mut options = Vec::new();
+
+        while let Some(arg) = args.next() {
+            let name = match arg.strip_prefix("--") {
+                Some(name) => name,
+                None => return Err(format!("unexpected argument `{}`", arg))
+            };
+
+            match name.find('=') {
+                Some(index) => options.push((name[..index].to_string(), Some(name[index + 1..].to_string()))),
+                None if name == "vsync" => options.push((name.to_string(), None)),
+                None => options.push((name.to_string(), args.next()))
+            }
+        }
+
+        Self::from_options(options)
+    }
+
+    /// Decodes `+` and `%XX` escapes, leaving malformed escapes as they are.
+    #[cfg(any(feature = "web", test))]
+    fn decode(value: &str) -> String {
+        let bytes = value.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+
+        while i < bytes.len() {
+            let escaped = bytes.get(i + 1..i + 3)
+                .filter(|_| bytes[i] == b'%')
+                .and_then(|x| std::str::from_utf8(x).ok())
+                .and_then(|x| u8::from_str_radix(x, 16).ok());
+
+            match (bytes[i], escaped) {
+                (_, Some(byte)) => {
+                    decoded.push(byte);
+                    i += 3;
+                },
+                (b'+', None) => {
+                    decoded.push(b' ');
+                    i += 1;
+                },
+                (byte, None) => {
+                    decoded.push(byte);
+                    i += 1;
+                }
+            }
+        }
+
+        String::from_utf8_lossy(&decoded).into_owned()
+    }
+
+    /// Reads a query string like `?flocks=4&vsync`, values are expected to be URL encoded.
+    #[cfg(any(feature = "web", test))]
+    pub fn from_query(query: &str) -> Result<Self, String> {
+        let options = query.trim_start_matches('?')
+            .split('&')
+            .filter(|x| !x.is_empty())
+            .map(|pair| {
+                let mut parts = pair.splitn(2, '=');
+                let name = parts.next().unwrap_or_default().to_string();
+                (name, parts.next().map(Self::decode))
+            });
+
+        Self::from_options(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn no_arguments_give_the_defaults() {
+        assert_eq!(ExampleConfig::from_args(&[]), Ok(ExampleConfig::default()));
+        assert_eq!(ExampleConfig::from_query(""), Ok(ExampleConfig::default()));
+        assert_eq!(ExampleConfig::from_query("?"), Ok(ExampleConfig::default()));
+    }
+
+    #[test]
+    fn arguments_are_read() {
+        let config = ExampleConfig::from_args(&args("--width 640 --flocks=3 --members 20 --seed 7 --headless 100 --metrics out.csv")).unwrap();
+
+        assert_eq!(config, ExampleConfig {
+            width: Some(640.0),
+            flocks: 3,
+            members: 20,
+            seed: Some(7),
+            headless_ticks: Some(100),
+            metrics: Some("out.csv".into()),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn vsync_takes_an_optional_value() {
+        assert!(ExampleConfig::from_args(&args("--vsync")).unwrap().vsync);
+        assert!(ExampleConfig::from_args(&args("--vsync --flocks 3")).unwrap().vsync);
+        assert!(!ExampleConfig::from_args(&args("--vsync=false")).unwrap().vsync);
+        assert!(ExampleConfig::from_args(&args("--vsync=maybe")).is_err());
+    }
+
+    #[test]
+    fn bad_arguments_are_rejected() {
+        assert_eq!(ExampleConfig::from_args(&args("--speed 3")), Err("unknown option `speed`".to_string()));
+        assert_eq!(ExampleConfig::from_args(&args("--members many")), Err("invalid value `many` for `members`".to_string()));
+        assert_eq!(ExampleConfig::from_args(&args("--members")), Err("missing value for `members`".to_string()));
+        assert_eq!(ExampleConfig::from_args(&args("flocks")), Err("unexpected argument `flocks`".to_string()));
+    }
+
+    #[test]
+    fn queries_are_read_and_decoded() {
+        let config = ExampleConfig::from_query("?flocks=4&members=50&vsync&preset=presets%2Fcalm+birds.flock").unwrap();
+
+        assert_eq!(config, ExampleConfig {
+            flocks: 4,
+            members: 50,
+            vsync: true,
+            preset: "presets/calm birds.flock".to_string(),
+            ..Default::default()
+        });
+        assert_eq!(ExampleConfig::decode("100%"), "100%");
+        assert!(ExampleConfig::from_query("?members=lots").is_err());
+    }
+}
@@ -0,0 +1,179 @@
+
+#[cfg(feature = "web")]
+extern crate wee_alloc;
+#[cfg(feature = "web")]
+extern crate console_error_panic_hook;
+
+#[cfg(feature = "web")]
+use std::panic;
+
+#[cfg(feature = "web")]
+#[global_allocator]
+static ALLOC: wee_alloc::WeeAlloc = wee_alloc::WeeAlloc::INIT;
+
+use bevy::prelude::*;
+
+use bevy_test::{ fps::*, plugins::* };
+
+mod config;
+mod simple_flocking;
+#[cfg(feature = "web")]
+mod viewport_resize;
+
+use config::ExampleConfig;
+use simple_flocking::SimpleExamplePlugin;
+
+#[cfg(not(feature = "web"))]
+fn read_config() -> ExampleConfig {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    if args.iter().any(|x| x == "--help") {
+        println!("{}", config::USAGE);
+        std::process::exit(0);
+    }
+
+    match ExampleConfig::from_args(&args) {
+        Ok(config) => config,
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, config::USAGE);
+            std::process::exit(2);
+        }
+    }
+}
+
+#[cfg(feature = "web")]
+fn read_config() -> ExampleConfig {
+    let query = web_sys::window().unwrap().location().search().unwrap_or_default();
+
+    // Logging is not set up before the app is built, so warn on the browser console directly
+    ExampleConfig::from_query(&query).unwrap_or_else(|error| {
+        web_sys::console::warn_1(&format!("invalid query `{}`, using the default config: {}", query, error).into());
+        ExampleConfig::default()
+    })
+}
+
+fn metrics_config(path: &std::path::Path) -> FlockMetricsConfig {
+    let format = match path.extension().and_then(|x| x.to_str()) {
+        Some("json") | Some("jsonl") => FlockMetricsFormat::JsonLines,
+        _ => FlockMetricsFormat::Csv
+    };
+
+    FlockMetricsConfig {
+        path: path.to_path_buf(),
+        format,
+        ..Default::default()
+    }
+}
+
+/// Reads an asset from where Bevy's asset server would, so `--preset` names the same file with and without a window.
+#[cfg(not(feature = "web"))]
+fn read_asset(path: &str) -> std::io::Result<Vec<u8>> {
+    let root = std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::current_exe().ok().and_then(|x| x.parent().map(|x| x.to_path_buf())))
+        .unwrap_or_default();
+
+    std::fs::read(root.join("assets").join(path))
+}
+
+/// Runs the simulation without a window or any rendering. The preset is read once up front, there is no hot reloading.
+#[cfg(not(feature = "web"))]
+fn run_headless(config: &ExampleConfig, ticks: u64) {
+    use bevy_test::{ bidimensional::{ Flock, FlockPreset, FlockSpawnerPlugin, SimRng }, headless::HeadlessSimulation };
+
+    let preset = read_asset(&config.preset)
+        .map_err(|error| error.to_string())
+        .and_then(|bytes| FlockPreset::from_bytes(&bytes).map_err(|error| error.to_string()));
+    let preset = match preset {
+        Ok(preset) => preset,
+        Err(error) => {
+            eprintln!("error: could not read preset `{}`: {}", config.preset, error);
+            std::process::exit(2);
+        }
+    };
+
+    let width = config.width.unwrap_or(ExampleConfig::DEFAULT_WIDTH);
+    let height = config.height.unwrap_or(ExampleConfig::DEFAULT_HEIGHT);
+
+    let mut simulation = HeadlessSimulation::with_plugins(width, height, |app| {
+        let rng = config.seed.map(SimRng::new).unwrap_or_default();
+        // Headless runs have no log subscriber, so this goes to stderr next to any errors
+        eprintln!("simulation seed: {}", rng.seed());
+
+        app
+            .add_resource(rng)
+            .add_plugin(FlockSpawnerPlugin);
+
+        if let Some(path) = &config.metrics {
+            app.add_plugin(FlockMetricsPlugin::new(metrics_config(path)));
+        }
+    });
+
+    for (_, spawner) in SimpleExamplePlugin::flocks(config) {
+        simulation.app.world.spawn((Flock::from(&preset), spawner));
+    }
+
+    simulation.step(ticks as usize);
+}
+
+#[bevy_main]
+fn main() {
+    #[cfg(feature = "web")]
+    panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    let config = read_config();
+
+    #[cfg(not(feature = "web"))]
+    {
+        if let Some(ticks) = config.headless_ticks {
+            run_headless(&config, ticks);
+            return;
+        }
+    }
+
+    let (mut width, mut height) = (ExampleConfig::DEFAULT_WIDTH, ExampleConfig::DEFAULT_HEIGHT);
+    let mut app = App::build();
+
+    #[cfg(feature = "web")]
+    {
+        let window: web_sys::Window = web_sys::window().unwrap();
+        let document_element = window.document().unwrap().document_element().unwrap();
+        width = document_element.client_width() as f32;
+        height = document_element.client_height() as f32;
+    }
+
+    app
+        .add_resource(WindowDescriptor {
+            title: "Flocking Example".to_string(),
+            width: config.width.unwrap_or(width),
+            height: config.height.unwrap_or(height),
+            vsync: config.vsync,
+            resizable: true,
+            ..Default::default()
+        })
+        .add_plugins(DefaultPlugins);
+    
+    #[cfg(feature = "web")]
+    app
+        .add_plugin(bevy_webgl2::WebGL2Plugin)
+        .add_plugin(viewport_resize::ViewportResizedPlugin);
+
+    if let Some(path) = &config.metrics {
+        app.add_plugin(FlockMetricsPlugin::new(metrics_config(path)));
+    }
+
+    app
+        .add_plugin(OnScreenFpsPlugin::new(OnScreenFpsConfig {
+            text_style: TextStyle {
+                font_size: 18.0,
+                color: Color::WHITE,
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .add_plugin(DebugDrawPlugin::default())
+        .add_plugin(SimpleExamplePlugin::new(config))
+        .add_plugin(TuningPanelPlugin::default())
+        .add_plugin(PointerInteractionPlugin::default())
+        .run();
+}
@@ -0,0 +1,175 @@
+use bevy::{prelude::*, window::WindowResized};
+
+use bevy_test::bidimensional::{Flock, FlockMemberMarker, FlockMemberParams, FlockPreset, FlockPresetPlugin, FlockSceneCommand, FlockScenePlugin, FlockSpawner, FlockSpawnerPlugin, FlockingPlugin, ForceFieldPlugin, MovementPlugin, ParamDistribution, SimRng, SimulationClock, SpawnShape};
+use bevy_test::plugins::TuningPanelFocus;
+
+use crate::config::ExampleConfig;
+
+/// F5 saves here rather than over the shipped scene, F9 loads it once it exists.
+const SAVED_SCENE_FILE: &'static str = "assets/scenes/saved.scn";
+const SAVED_SCENE_ASSET: &'static str = "scenes/saved.scn";
+const SCENE_ASSET: &'static str = "scenes/flocks.scn";
+
+struct BackgroundMarker;
+
+const FLOCK_COLORS: [Color; 4] = [Color::RED, Color::BLUE, Color::GREEN, Color::rgb(1.0, 1.0, 0.0)];
+
+pub struct SimpleExamplePlugin(ExampleConfig);
+
+impl SimpleExamplePlugin {
+    pub fn new(config: ExampleConfig) -> Self {
+        Self(config)
+    }
+
+    /// The flocks to spawn, without any sprites.
+    pub fn flocks(config: &ExampleConfig) -> Vec<(Flock, FlockSpawner)> {
+        (0..config.flocks)
+            .map(|_| (Flock {
+                flock_radius: 50.0,
+                alignment_strength: 1.0,
+                cohesion_strength: 1.0,
+                separation_strength: 1.0
+            }, FlockSpawner {
+                count: config.members,
+                ..Self::member_spawner()
+            }))
+            .collect()
+    }
+
+    fn setup(commands: &mut Commands, config: Res<ExampleConfig>, window: Res<WindowDescriptor>, mut materials: ResMut<Assets<ColorMaterial>>, asset_server: Res<AssetServer>) {
+        let ship_handle = asset_server.load("sprite/ship.png");
+        let preset_handle: Handle<FlockPreset> = asset_server.load(config.preset.as_str());
+
+        #[cfg(not(feature = "web"))]
+        {
+            if let Err(error) = asset_server.watch_for_changes() {
+                warn!("could not watch assets for changes: {:?}", error);
+            }
+        }
+
+        commands
+            .spawn(Camera2dBundle::default())
+
+            // Background
+            .spawn(SpriteBundle {
+                material: materials.add(ColorMaterial {
+                    texture: Some(asset_server.load("sprite/spacefield.png")),
+                    color: Color::default()
+                }),
+                sprite: Sprite::new(Vec2::new(window.width, window.height)),
+                ..Default::default()
+            }).with(BackgroundMarker);
+
+        for (i, (flock, spawner)) in Self::flocks(&config).into_iter().enumerate() {
+            commands.spawn((flock, preset_handle.clone(), FlockSpawner {
+                material: Some(materials.add(ColorMaterial {
+                    color: FLOCK_COLORS[i % FLOCK_COLORS.len()],
+                    texture: Some(ship_handle.clone())
+                })),
+                ..spawner
+            }));
+        }
+    }
+
+    fn member_spawner() -> FlockSpawner {
+        FlockSpawner {
+            count: 99,
+            shape: SpawnShape::Rect { half_extents: Vec2::new(100.0, 100.0) },
+            size: ParamDistribution::Uniform(12.0, 20.0),
+            reference_size: Some(12.0),
+            max_speed: ParamDistribution::Fixed(200.0),
+            max_accel: ParamDistribution::Fixed(100.0),
+            safe_radius: ParamDistribution::Fixed(60.0),
+            initial_velocity: ParamDistribution::Uniform(-2.0, 2.0),
+            ..Default::default()
+        }
+    }
+
+    fn scene_keys(keys: Res<Input<KeyCode>>, mut scene_commands: ResMut<Events<FlockSceneCommand>>) {
+        if keys.just_pressed(KeyCode::F5) {
+            scene_commands.send(FlockSceneCommand::Save(SAVED_SCENE_FILE.into()));
+        }
+
+        if keys.just_pressed(KeyCode::F9) {
+            let asset = if std::path::Path::new(SAVED_SCENE_FILE).exists() { SAVED_SCENE_ASSET } else { SCENE_ASSET };
+            scene_commands.send(FlockSceneCommand::Load(asset.to_string()));
+        }
+    }
+
+    /// Space pauses, Period steps a single tick, `[` and `]` halve and double the speed and Backslash resets it.
+    fn clock_keys(keys: Res<Input<KeyCode>>, focus: Res<TuningPanelFocus>, mut clock: ResMut<SimulationClock>) {
+        if focus.0 {
+            return;
+        }
+
+        if keys.just_pressed(KeyCode::Space) {
+            clock.toggle_pause();
+        }
+
+        if keys.just_pressed(KeyCode::Period) {
+            clock.step();
+        }
+
+        if keys.just_pressed(KeyCode::LBracket) {
+            clock.scale_time(0.5);
+        }
+
+        if keys.just_pressed(KeyCode::RBracket) {
+            clock.scale_time(2.0);
+        }
+
+        if keys.just_pressed(KeyCode::Backslash) {
+            clock.time_scale = 1.0;
+        }
+    }
+
+    /// Members loaded from a scene carry no sprite, so give them one.
+    fn dress_loaded_members(commands: &mut Commands, mut materials: ResMut<Assets<ColorMaterial>>, asset_server: Res<AssetServer>, query: Query<(Entity, &GlobalTransform, &FlockMemberParams, &Parent), (With<FlockMemberMarker>, Without<Sprite>)>) {
+        for (entity, transform, params, parent) in query.iter() {
+            let size = params.safe_radius / 5.0;
+            commands.insert(entity, SpriteBundle {
+                material: materials.add(ColorMaterial {
+                    color: FLOCK_COLORS[parent.0.id() as usize % FLOCK_COLORS.len()],
+                    texture: Some(asset_server.load("sprite/ship.png"))
+                }),
+                visible: Visible {
+                    is_transparent: true,
+                    ..Default::default()
+                },
+                sprite: Sprite::new(Vec2::new(size, size)),
+                global_transform: *transform,
+                ..Default::default()
+            });
+        }
+    }
+
+    fn resized(mut reader: Local<EventReader<WindowResized>>, resize_event: Res<Events<WindowResized>>, mut query: Query<&mut Sprite, With<BackgroundMarker>>) {
+        for event in reader.iter(&resize_event) {
+            for mut sprite in query.iter_mut() {
+                sprite.size = Vec2::new(event.width, event.height);
+            }
+        }
+    }
+}
+
+impl Plugin for SimpleExamplePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let rng = self.0.seed.map(SimRng::new).unwrap_or_default();
+        info!("simulation seed: {}", rng.seed());
+
+        app
+            .add_resource(self.0.clone())
+            .add_resource(rng)
+            .add_plugin(MovementPlugin)
+            .add_plugin(FlockingPlugin::with_wrapping())
+            .add_plugin(ForceFieldPlugin)
+            .add_plugin(FlockSpawnerPlugin)
+            .add_plugin(FlockPresetPlugin)
+            .add_plugin(FlockScenePlugin)
+            .add_startup_system(Self::setup.system())
+            .add_system(Self::scene_keys.system())
+            .add_system(Self::clock_keys.system())
+            .add_system(Self::dress_loaded_members.system())
+            .add_system(Self::resized.system());
+    }
+}
@@ -0,0 +1,95 @@
+use bevy::prelude::*;
+
+/// Simulation time as seen by `flocking` and `movement`. Unlike `Time` it can be paused, single-stepped
+/// and run in slow motion or fast forward through `time_scale`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationClock {
+    pub paused: bool,
+    pub step_seconds: f32,
+    /// Multiplies the time passed each tick, single steps are not scaled.
+    pub time_scale: f32,
+    /// Advances by this much every tick instead of the real frame time, for deterministic runs.
+    pub fixed_delta_seconds: Option<f32>,
+    step_requested: bool,
+    delta_seconds: f32,
+    ticks: u64,
+    elapsed_seconds: f64
+}
+
+impl Default for SimulationClock {
+    fn default() -> Self {
+        SimulationClock {
+            paused: false,
+            step_seconds: 1.0 / 60.0,
+            time_scale: 1.0,
+            fixed_delta_seconds: None,
+            step_requested: false,
+            delta_seconds: 0.0,
+            ticks: 0,
+            elapsed_seconds: 0.0
+        }
+    }
+}
+
+impl SimulationClock {
+    pub const MIN_TIME_SCALE: f32 = 1.0 / 16.0;
+    pub const MAX_TIME_SCALE: f32 = 16.0;
+
+    /// A clock advancing by exactly `delta_seconds` every tick.
+    pub fn fixed(delta_seconds: f32) -> Self {
+        SimulationClock {
+            fixed_delta_seconds: Some(delta_seconds),
+            ..Default::default()
+        }
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta_seconds
+    }
+
+    /// Ticks that advanced the simulation, paused ticks are not counted.
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    /// Simulated seconds since startup, the sum of every `delta_seconds`.
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.elapsed_seconds
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Multiplies `time_scale` by `factor`, within `MIN_TIME_SCALE` and `MAX_TIME_SCALE`.
+    pub fn scale_time(&mut self, factor: f32) {
+        self.time_scale = (self.time_scale * factor).max(Self::MIN_TIME_SCALE).min(Self::MAX_TIME_SCALE);
+    }
+
+    /// Pauses the simulation and advances it by a single tick of `step_seconds`.
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.step_requested = true;
+    }
+
+    fn update(&mut self, real_delta_seconds: f32) {
+        self.delta_seconds = if self.step_requested {
+            self.step_seconds
+        } else if self.paused {
+            0.0
+        } else {
+            self.fixed_delta_seconds.unwrap_or(real_delta_seconds) * self.time_scale
+        };
+
+        self.step_requested = false;
+
+        if self.delta_seconds > 0.0 {
+            self.ticks += 1;
+            self.elapsed_seconds += self.delta_seconds as f64;
+        }
+    }
+}
+
+pub(crate) fn simulation_clock(time: Res<Time>, mut clock: ResMut<SimulationClock>) {
+    clock.update(time.delta_seconds());
+}
@@ -0,0 +1,203 @@
+use bevy::prelude::*;
+
+use crate::util::*;
+use super::{ Flock, FlockGoal, FlockMemberMarker, Formation, WorldBounds };
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlockClusteringConfig {
+    pub interval: u32,
+    pub neighbor_radius: f32,
+    pub merge_distance: f32,
+    pub min_cluster_size: usize
+}
+
+impl Default for FlockClusteringConfig {
+    fn default() -> Self {
+        FlockClusteringConfig {
+            interval: 30,
+            neighbor_radius: 150.0,
+            merge_distance: 100.0,
+            min_cluster_size: 3
+        }
+    }
+}
+
+/// A split flock carries over its `Flock`, `Formation` and `FlockGoal`, `FlockPresetPlugin` copies its preset
+/// when it sees the `Split` event.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FlockClusteringEvent {
+    Split { from: Entity, into: Entity },
+    Merged { from: Entity, into: Entity }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FlockClusteringPlugin(FlockClusteringConfig);
+
+impl FlockClusteringPlugin {
+    pub fn new(config: FlockClusteringConfig) -> Self {
+        Self(config)
+    }
+
+    fn find(parents: &mut [usize], mut index: usize) -> usize {
+        while parents[index] != index {
+            parents[index] = parents[parents[index]];
+            index = parents[index];
+        }
+
+        index
+    }
+
+    /// Groups `positions` into connected components, where two members are connected
+    /// if they are within `radius` of each other. Largest component first.
+    fn connected_components(positions: &[Vec2], radius: f32, bounds: Bounds<Vec2>) -> Vec<Vec<usize>> {
+        let mut parents: Vec<usize> = (0..positions.len()).collect();
+
+        for i in 0..positions.len() {
+            for j in (i + 1)..positions.len() {
+                if positions[i].bound_to(positions[j], bounds).length_squared() < radius * radius {
+                    let (a, b) = (Self::find(&mut parents, i), Self::find(&mut parents, j));
+                    if a != b {
+                        parents[b] = a;
+                    }
+                }
+            }
+        }
+
+        let mut components: Vec<Vec<usize>> = Vec::new();
+        let mut roots: Vec<usize> = Vec::new();
+        for i in 0..positions.len() {
+            let root = Self::find(&mut parents, i);
+            match roots.iter().position(|x| *x == root) {
+                Some(component) => components[component].push(i),
+                None => {
+                    roots.push(root);
+                    components.push(vec![i]);
+                }
+            }
+        }
+
+        components.sort_by(|a, b| b.len().cmp(&a.len()));
+        components
+    }
+
+    fn centroid(positions: &[Vec2], bounds: Bounds<Vec2>) -> Vec2 {
+        let mut centroid = Vec2::zero();
+
+        for (count, position) in positions.iter().enumerate() {
+            let mut current_average = centroid;
+            if count > 0 {
+                current_average = (current_average / count as f32).bound_to(Vec2::zero(), bounds);
+            }
+
+            centroid += position.bound_to(current_average, bounds);
+        }
+
+        if positions.len() > 0 {
+            centroid = (centroid / positions.len() as f32).bound_to(Vec2::zero(), bounds);
+        }
+
+        centroid
+    }
+
+    fn clustering(
+        commands: &mut Commands,
+        config: Res<FlockClusteringConfig>,
+        world_bounds: Res<WorldBounds>,
+        mut events: ResMut<Events<FlockClusteringEvent>>,
+        mut ticks: Local<u32>,
+        query: Query<(Entity, &Flock, &Children, Option<&Formation>, Option<&FlockGoal>)>,
+        mut child_query: Query<(&GlobalTransform, &mut Parent), With<FlockMemberMarker>>
+    ) {
+        *ticks += 1;
+        if *ticks < config.interval.max(1) {
+            return;
+        }
+        *ticks = 0;
+
+        let bounds = world_bounds.bounds;
+        let mut unsplit: Vec<(Entity, Vec<Entity>, Vec2)> = Vec::new();
+
+        for (entity, flock, children, formation, goal) in query.iter() {
+            let mut members = Vec::new();
+            let mut positions = Vec::new();
+
+            for child in children.iter() {
+                if let Ok((transform, _)) = child_query.get_mut(*child) {
+                    members.push(*child);
+                    positions.push(transform.translation.truncate());
+                }
+            }
+
+            let components = Self::connected_components(&positions, config.neighbor_radius, bounds);
+            let mut split = false;
+
+            for component in components.iter().skip(1).filter(|x| x.len() >= config.min_cluster_size.max(1)) {
+                commands.spawn((*flock, ));
+                if let Some(formation) = formation {
+                    commands.with(formation.clone());
+                }
+                if let Some(goal) = goal {
+                    commands.with(*goal);
+                }
+                let new_flock = commands.current_entity().unwrap();
+
+                for index in component.iter() {
+                    if let Ok((_, mut parent)) = child_query.get_mut(members[*index]) {
+                        parent.0 = new_flock;
+                    }
+                }
+
+                events.send(FlockClusteringEvent::Split { from: entity, into: new_flock });
+                split = true;
+            }
+
+            if !split && members.len() > 0 {
+                let centroid = Self::centroid(&positions, bounds);
+                unsplit.push((entity, members, centroid));
+            }
+        }
+
+        // Larger flocks absorb smaller ones, and each flock takes part in at most one merge per pass.
+        unsplit.sort_by(|a, b| b.1.len().cmp(&a.1.len()));
+        let mut merged = vec![false; unsplit.len()];
+
+        for i in 0..unsplit.len() {
+            if merged[i] {
+                continue;
+            }
+
+            for j in (i + 1)..unsplit.len() {
+                if merged[j] {
+                    continue;
+                }
+
+                let (into, _, centroid) = &unsplit[i];
+                let (from, members, other_centroid) = &unsplit[j];
+
+                if centroid.bound_to(*other_centroid, bounds).length_squared() < config.merge_distance * config.merge_distance {
+                    for member in members.iter() {
+                        if let Ok((_, mut parent)) = child_query.get_mut(*member) {
+                            parent.0 = *into;
+                        }
+                    }
+
+                    commands.despawn(*from);
+                    events.send(FlockClusteringEvent::Merged { from: *from, into: *into });
+
+                    merged[i] = true;
+                    merged[j] = true;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl Plugin for FlockClusteringPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_resource(self.0)
+            .add_event::<FlockClusteringEvent>()
+            .add_system(Self::clustering.system());
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::util::*;
+use super::{ FlockMemberMarker, FlockMemberParams, FlockSnapshot, Velocity, WorldBounds, movement };
+
+/// Runs right after `MOVEMENT_STAGE`, so collisions resolve the positions members just moved to.
+pub const COLLISION_STAGE: &'static str = "COLLISION";
+
+/// Members collide as circles of `safe_radius * radius_scale`. A `restitution` of 0 stops them along the contact normal,
+/// 1 bounces them off each other without losing speed.
+#[derive(Debug, Clone)]
+pub struct MemberCollisionConfig {
+    pub radius_scale: f32,
+    pub restitution: f32,
+    pub between_flocks: bool
+}
+
+impl Default for MemberCollisionConfig {
+    fn default() -> Self {
+        MemberCollisionConfig {
+            radius_scale: 0.25,
+            restitution: 0.5,
+            between_flocks: true
+        }
+    }
+}
+
+/// Sent once per overlapping pair and tick. `normal` points from `b` to `a`, `impulse` is the velocity change applied
+/// to each member along it, zero when they were already moving apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MemberCollision {
+    pub a: Entity,
+    pub b: Entity,
+    pub point: Vec2,
+    pub normal: Vec2,
+    pub penetration: f32,
+    pub impulse: f32
+}
+
+/// Must be added after `MovementPlugin`, which adds the stage collisions run after.
+#[derive(Clone, Debug, Default)]
+pub struct MemberCollisionPlugin(MemberCollisionConfig);
+
+impl MemberCollisionPlugin {
+    pub fn new(config: MemberCollisionConfig) -> Self {
+        Self(config)
+    }
+
+    fn member_collisions(
+        config: Res<MemberCollisionConfig>,
+        world_bounds: Res<WorldBounds>,
+        mut events: ResMut<Events<MemberCollision>>,
+        mut query: Query<(Entity, &mut GlobalTransform, &mut Velocity, &FlockMemberParams, Option<&Parent>), With<FlockMemberMarker>>
+    ) {
+        let bounds = world_bounds.bounds;
+        let mut snapshot = FlockSnapshot::default();
+        let mut colliders = Vec::new();
+
+        for (entity, transform, velocity, params, parent) in query.iter_mut() {
+            if params.safe_radius * config.radius_scale > 0.0 {
+                snapshot.push(entity.id(), transform.translation.truncate(), velocity.0, Vec2::zero(), params);
+                colliders.push((entity, parent.map(|x| x.0)));
+            }
+        }
+
+        let radius = |index: usize| snapshot.safe_radii[index] * config.radius_scale;
+        let mut corrections = vec![(Vec2::zero(), Vec2::zero()); snapshot.len()];
+
+        // Buckets at least as wide as the largest contact, so members only need to look at the 3x3 buckets around them
+        let size = bounds.upper() - bounds.lower();
+        let contact = (0..snapshot.len()).map(radius).fold(0.0, f32::max) * 2.0;
+        let columns = ((size.x / contact) as isize).max(1);
+        let rows = ((size.y / contact) as isize).max(1);
+        let bucket = |position: Vec2| {
+            let cell = (position - bounds.lower()) / size;
+            (((cell.x * columns as f32).floor() as isize).rem_euclid(columns), ((cell.y * rows as f32).floor() as isize).rem_euclid(rows))
+        };
+
+        let mut buckets: HashMap<(isize, isize), Vec<usize>> = HashMap::new();
+        for index in 0..snapshot.len() {
+            buckets.entry(bucket(snapshot.position(index))).or_default().push(index);
+        }
+
+        for a in 0..snapshot.len() {
+            // Neighboring buckets wrap around the edges, and repeat themselves in worlds fewer than 3 buckets across
+            let (x, y) = bucket(snapshot.position(a));
+            let mut neighbors: Vec<(isize, isize)> = (-1..=1)
+                .flat_map(|dy| (-1..=1).map(move |dx| ((x + dx).rem_euclid(columns), (y + dy).rem_euclid(rows))))
+                .collect();
+            neighbors.sort();
+            neighbors.dedup();
+
+            for b in neighbors.iter().filter_map(|x| buckets.get(x)).flatten().copied() {
+                // Every pair is seen from both sides, only the lower index resolves it
+                if b <= a {
+                    continue;
+                }
+
+                if !config.between_flocks && colliders[a].1 != colliders[b].1 {
+                    continue;
+                }
+
+                // Measured across the edges, members on opposite sides of the world can touch
+                let difference = snapshot.position(a).bound_to(snapshot.position(b), bounds);
+                let distance = difference.length();
+                let penetration = radius(a) + radius(b) - distance;
+                if penetration <= 0.0 {
+                    continue;
+                }
+
+                // Coincident members get pushed apart along an arbitrary but stable axis
+                let normal = if distance > 0.0 { difference / distance } else { Vec2::unit_x() };
+                let approach = (snapshot.velocities[a] - snapshot.velocities[b]).dot(normal);
+                let impulse = if approach < 0.0 { -(1.0 + config.restitution) * approach / 2.0 } else { 0.0 };
+
+                corrections[a].0 += normal * penetration / 2.0;
+                corrections[a].1 += normal * impulse;
+                corrections[b].0 -= normal * penetration / 2.0;
+                corrections[b].1 -= normal * impulse;
+
+                events.send(MemberCollision {
+                    a: colliders[a].0,
+                    b: colliders[b].0,
+                    point: snapshot.position(b) + normal * (radius(b) - penetration / 2.0),
+                    normal,
+                    penetration,
+                    impulse
+                });
+            }
+        }
+
+        for ((entity, _), (offset, impulse)) in colliders.into_iter().zip(corrections.into_iter()) {
+            if offset == Vec2::zero() && impulse == Vec2::zero() {
+                continue;
+            }
+
+            if let Ok((_, mut transform, mut velocity, _, _)) = query.get_mut(entity) {
+                transform.translation += offset.extend(0.0);
+                velocity.0 += impulse;
+            }
+        }
+    }
+}
+
+impl Plugin for MemberCollisionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_resource(self.0.clone())
+            .init_resource::<WorldBounds>()
+            .add_event::<MemberCollision>()
+            .add_stage_after(movement::MOVEMENT_STAGE, COLLISION_STAGE, SystemStage::serial())
+            .add_system_to_stage(COLLISION_STAGE, Self::member_collisions.system());
+    }
+}
@@ -0,0 +1,351 @@
+use bevy::{ prelude::*, tasks::ComputeTaskPool, utils::Instant };
+
+use crate::util::*;
+use super::{ FlockSnapshot, SimulationClock, Velocity, WorldBounds, movement };
+
+pub const STEERING_STAGE: &'static str = "STEERING";
+
+#[derive(Debug, Default, PartialEq, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct FlockMemberMarker;
+
+/// Steering requested by systems other than `flocking`, on the same scale as the flocking rules.
+/// It is added to them by `flocking` and reset every tick, so systems in `STEERING_STAGE` should accumulate into it.
+#[derive(Debug, Default, PartialEq, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct ExternalSteering(pub Vec2);
+
+#[derive(Debug, Default, PartialEq, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct FlockMemberParams {
+    pub max_speed: f32,
+    pub max_accel: f32,
+    pub safe_radius: f32
+}
+
+/// Steering contributions from the last `flocking` tick, scaled to acceleration like the sum `flocking` applies.
+/// Only members carrying this component get it written, it is meant for debugging.
+#[derive(Debug, Default, PartialEq, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct FlockSteering {
+    pub alignment: Vec2,
+    pub cohesion: Vec2,
+    pub separation: Vec2
+}
+
+#[derive(Bundle, Clone, Debug)]
+pub struct FlockMember {
+    pub marker: FlockMemberMarker,
+    pub velocity: Velocity,
+    pub steering: ExternalSteering,
+    pub params: FlockMemberParams
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct Flock {
+    pub flock_radius: f32,
+    pub alignment_strength: f32,
+    pub cohesion_strength: f32,
+    pub separation_strength: f32
+}
+
+#[derive(Debug, Default, PartialEq, Clone, Copy, Reflect)]
+#[reflect(Component)]
+pub struct FlockStats {
+    pub centroid: Vec2,
+    pub heading: Vec2,
+    pub spread: f32,
+    pub bounding_radius: f32,
+    pub polarization: f32,
+    pub angular_momentum: f32,
+    pub member_count: usize
+}
+
+impl Default for FlockMember {
+    fn default() -> Self {
+        FlockMember {
+            marker: FlockMemberMarker,
+            velocity: Vec2::zero().into(),
+            steering: ExternalSteering::default(),
+            params: FlockMemberParams {
+                max_speed: 200.0,
+                max_accel: 30.0,
+                safe_radius: 50.0
+            }
+        }
+    }
+}
+
+/// Number of members steered per task on the `ComputeTaskPool`. Batches run in parallel within each flock,
+/// the result does not depend on the batch size or the number of threads.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlockingBatchSize(pub usize);
+
+impl Default for FlockingBatchSize {
+    fn default() -> Self {
+        FlockingBatchSize(256)
+    }
+}
+
+/// Work done by the last `flocking` tick, for diagnostics.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FlockingProfile {
+    pub seconds: f64,
+    /// Ordered member pairs separation runs over, the square of each flock's size summed over flocks.
+    pub neighbor_pairs: usize
+}
+
+#[derive(Default)]
+pub struct FlockingPlugin {
+    include_wrapping: bool
+}
+
+impl FlockingPlugin {
+    pub fn new(include_wrapping: bool) -> FlockingPlugin {
+        FlockingPlugin {
+            include_wrapping
+        }
+    }
+
+    pub fn with_wrapping() -> FlockingPlugin {
+        Self::new(true)
+    }
+
+    #[inline]
+    pub fn calculate_alignment(max_speed: f32, average_forward: Vec2) -> Vec2 {
+        let mut alignment: Vec2  = average_forward / max_speed;
+
+        if alignment.length_squared() > 1.0 {
+            alignment = alignment.normalize();
+        }
+    
+        alignment
+    }
+
+    #[inline]
+    pub fn calculate_cohesion(position: Vec2, average_position: Vec2, flock_radius: f32) -> Vec2 {
+        let mut cohesion: Vec2 = average_position - position;
+    
+        if cohesion.length_squared() < flock_radius * flock_radius {
+            cohesion /= flock_radius;
+        } else {
+            cohesion = cohesion.normalize();
+        }
+    
+        cohesion
+    }
+
+    /// Scalar reference for `FlockSnapshot::separation`.
+    #[inline]
+    pub fn calculate_separation(id: u32, params: &FlockMemberParams, position: Vec2, boids: &[(u32, Vec2, FlockMemberParams)]) -> Vec2 {
+        let mut separation = Vec2::zero();
+
+        for (other_id, other_position, other_params) in boids.into_iter() {
+            if other_id != &id {
+                let difference: Vec2 = position - *other_position;
+                let distance_squared = difference.length_squared();
+                let minimum_distance = params.safe_radius + other_params.safe_radius;
+
+                if distance_squared < minimum_distance * minimum_distance {
+                    separation += difference.normalize() * (minimum_distance - distance_squared.sqrt()) / minimum_distance;
+                }
+            }
+        }
+
+        if separation.length_squared() > 1.0 {
+            separation = separation.normalize();
+        }
+
+        separation
+    }
+
+    #[inline]
+    fn calculate_stats(snapshot: &FlockSnapshot, bounds: Bounds<Vec2>) -> FlockStats {
+        let mut stats = FlockStats {
+            centroid: snapshot.average_position.bound_to(Vec2::zero(), bounds),
+            heading: snapshot.average_forward,
+            member_count: snapshot.len(),
+            ..Default::default()
+        };
+
+        let mut direction_sum = Vec2::zero();
+        for (index, velocity) in snapshot.velocities.iter().enumerate() {
+            let offset = snapshot.offset(index);
+            let distance = offset.length();
+            stats.spread += distance;
+            stats.bounding_radius = stats.bounding_radius.max(distance);
+            stats.angular_momentum += offset.x * velocity.y - offset.y * velocity.x;
+
+            if velocity.length_squared() > 0.0 {
+                direction_sum += velocity.normalize();
+            }
+        }
+
+        if !snapshot.is_empty() {
+            stats.spread /= snapshot.len() as f32;
+            stats.angular_momentum /= snapshot.len() as f32;
+            stats.polarization = direction_sum.length() / snapshot.len() as f32;
+        }
+
+        stats
+    }
+
+    fn flocking(commands: &mut Commands, pool: Res<ComputeTaskPool>, batch_size: Res<FlockingBatchSize>, clock: Res<SimulationClock>, world_bounds: Res<WorldBounds>, mut profile: ResMut<FlockingProfile>, mut query: Query<(Entity, &Flock, &Children, Option<&mut FlockStats>)>, mut child_query: Query<(&mut Velocity, &GlobalTransform, &FlockMemberParams, Option<&mut FlockSteering>, Option<&mut ExternalSteering>), With<FlockMemberMarker>>) {
+        let bounds = world_bounds.bounds;
+        let delta_seconds = clock.delta_seconds();
+        let batch_size = batch_size.0.max(1);
+        let start = Instant::now();
+        let mut neighbor_pairs = 0;
+
+        for (entity, flock, children, stats) in query.iter_mut() {
+            let mut average_position = Vec2::zero();
+            let mut average_forward = Vec2::zero();
+            let mut snapshot = FlockSnapshot::with_capacity(children.len());
+            let mut members = Vec::with_capacity(children.len());
+
+            // Snapshot phase
+            for child in children.iter() {
+                if let Ok((velocity, transform, params, _, external)) = child_query.get_mut(*child) {
+                    let mut current_average = average_position;
+                    if !snapshot.is_empty() {
+                        current_average = (current_average / snapshot.len() as f32).bound_to(Vec2::zero(), bounds);
+                    }
+
+                    average_position += transform.translation.truncate().bound_to(current_average, bounds);
+                    average_forward += velocity.0;
+                    snapshot.push(child.id(), transform.translation.truncate(), velocity.0, external.map(|x| x.0).unwrap_or_default(), params);
+                    members.push(*child);
+                }
+            }
+
+            let mut new_stats = FlockStats::default();
+
+            if !snapshot.is_empty() {
+                average_position /= snapshot.len() as f32;
+                average_forward /= snapshot.len() as f32;
+                snapshot.set_averages(average_position, average_forward, bounds);
+
+                new_stats = Self::calculate_stats(&snapshot, bounds);
+                neighbor_pairs += snapshot.len() * snapshot.len();
+
+                // Steering phase, batches come back in the order they were spawned
+                let flock = *flock;
+                let snapshot = &snapshot;
+                let results: Vec<Vec<(Vec2, FlockSteering)>> = pool.scope(|scope| {
+                    for start in (0..snapshot.len()).step_by(batch_size) {
+                        let end = (start + batch_size).min(snapshot.len());
+                        scope.spawn(async move { snapshot.steer(&flock, start..end, delta_seconds) });
+                    }
+                });
+
+                // Write phase
+                for (member, (new_velocity, new_steering)) in members.iter().zip(results.into_iter().flatten()) {
+                    if let Ok((mut velocity, _, _, steering, external)) = child_query.get_mut(*member) {
+                        velocity.0 = new_velocity;
+
+                        if let Some(mut external) = external {
+                            external.0 = Vec2::zero();
+                        }
+
+                        if let Some(mut steering) = steering {
+                            *steering = new_steering;
+                        }
+                    }
+                }
+            }
+
+            match stats {
+                Some(mut stats) => *stats = new_stats,
+                None => { commands.insert_one(entity, new_stats); }
+            }
+        }
+
+        *profile = FlockingProfile {
+            seconds: start.elapsed().as_secs_f64(),
+            neighbor_pairs
+        };
+    }
+
+    fn wrapping(world_bounds: Res<WorldBounds>, mut query: Query<&mut GlobalTransform, With<FlockMemberMarker>>) {
+        let bounds = world_bounds.bounds;
+        for mut transform in query.iter_mut() {
+            transform.translation = transform.translation.truncate()
+                .bound_to(Vec2::zero(), bounds)
+                .extend(transform.translation.z);
+        }
+    }
+}
+
+impl Plugin for FlockingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .register_type::<Flock>()
+            .register_type::<FlockStats>()
+            .register_type::<FlockSteering>()
+            .register_type::<FlockMemberMarker>()
+            .register_type::<FlockMemberParams>()
+            .register_type::<ExternalSteering>()
+            .init_resource::<FlockingBatchSize>()
+            .init_resource::<FlockingProfile>()
+            .init_resource::<SimulationClock>()
+            .init_resource::<WorldBounds>()
+            .add_stage_before(stage::UPDATE, STEERING_STAGE, SystemStage::parallel())
+            .add_system(Self::flocking.system());
+
+        if self.include_wrapping {
+            app.add_system_to_stage(movement::MOVEMENT_STAGE, Self::wrapping.system());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn vector(range: f32) -> impl Strategy<Value = Vec2> {
+        (-range..range, -range..range).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    fn params() -> impl Strategy<Value = FlockMemberParams> {
+        (1.0f32..500.0, 1.0f32..500.0, 0.0f32..100.0).prop_map(|(max_speed, max_accel, safe_radius)| FlockMemberParams {
+            max_speed,
+            max_accel,
+            safe_radius
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn alignment_is_at_most_one(max_speed in 0.1f32..1000.0, average_forward in vector(1e4)) {
+            prop_assert!(FlockingPlugin::calculate_alignment(max_speed, average_forward).length() <= 1.0 + 1e-5);
+        }
+
+        #[test]
+        fn cohesion_is_at_most_one(position in vector(1e4), average_position in vector(1e4), flock_radius in 1.0f32..500.0) {
+            prop_assert!(FlockingPlugin::calculate_cohesion(position, average_position, flock_radius).length() <= 1.0 + 1e-5);
+        }
+
+        #[test]
+        fn separation_is_zero_beyond_minimum_distance(params in params(), other in params(), position in vector(1e4), direction in vector(1.0), extra in 0.0f32..1e3) {
+            prop_assume!(direction.length_squared() > 1e-4);
+
+            let minimum_distance = params.safe_radius + other.safe_radius;
+            let other_position = position + direction.normalize() * (minimum_distance + 1e-2 * minimum_distance.max(1.0) + extra);
+            let boids = [(0, position, params), (1, other_position, other)];
+
+            prop_assert_eq!(FlockingPlugin::calculate_separation(0, &params, position, &boids), Vec2::zero());
+        }
+
+        #[test]
+        fn separation_is_at_most_one(params in params(), others in prop::collection::vec((vector(200.0), params()), 1..20)) {
+            let mut boids = vec![(0, Vec2::zero(), params)];
+            boids.extend(others.into_iter().enumerate().map(|(i, (position, params))| (i as u32 + 1, position, params)));
+
+            let separation = FlockingPlugin::calculate_separation(0, &params, Vec2::zero(), &boids);
+            prop_assert!(!(separation.length() > 1.0 + 1e-5));
+        }
+    }
+}
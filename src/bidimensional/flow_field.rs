@@ -0,0 +1,340 @@
+use std::{ cmp::Reverse, collections::BinaryHeap };
+
+use bevy::prelude::*;
+#[cfg(feature = "render")]
+use bevy::render::texture::TextureFormat;
+
+use super::{ ExternalSteering, FlockFilter, FlockMemberMarker, FlockMemberParams, STEERING_STAGE, Velocity };
+
+const NEIGHBOR_OFFSETS: &[(isize, isize)] = &[(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)];
+
+/// A grid of desired velocities. Members inside the grid steer towards the velocity sampled at their position,
+/// scaled by `strength` on the same scale as the flocking rules.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlowField {
+    pub origin: Vec2,
+    pub cell_size: f32,
+    pub width: usize,
+    pub height: usize,
+    pub vectors: Vec<Vec2>,
+    pub strength: f32,
+    pub flocks: FlockFilter
+}
+
+impl FlowField {
+    const STRAIGHT_COST: u32 = 10;
+    const DIAGONAL_COST: u32 = 14;
+
+    /// A field of zero vectors, with `origin` at the lower left corner of the grid.
+    pub fn new(origin: Vec2, cell_size: f32, width: usize, height: usize) -> Self {
+        FlowField {
+            origin,
+            cell_size,
+            width,
+            height,
+            vectors: vec![Vec2::zero(); width * height],
+            strength: 1.0,
+            flocks: FlockFilter::All
+        }
+    }
+
+    /// Evaluates `function` at the world position of each cell's center.
+    pub fn from_fn(origin: Vec2, cell_size: f32, width: usize, height: usize, function: impl Fn(Vec2) -> Vec2) -> Self {
+        let mut field = Self::new(origin, cell_size, width, height);
+
+        for y in 0..height {
+            for x in 0..width {
+                field.vectors[y * width + x] = function(field.cell_center(x, y));
+            }
+        }
+
+        field
+    }
+
+    /// Reads directions from the red and green channels of an RGBA8 image, mapping `0..=255` to `-max_speed..=max_speed`.
+    /// The top row of the image is the top row of the grid.
+    #[cfg(feature = "render")]
+    pub fn from_image(texture: &Texture, origin: Vec2, cell_size: f32, max_speed: f32) -> Option<Self> {
+        if texture.format != TextureFormat::Rgba8UnormSrgb && texture.format != TextureFormat::Rgba8Unorm {
+            return None;
+        }
+
+        let (width, height) = (texture.size.width as usize, texture.size.height as usize);
+        if texture.data.len() < width * height * 4 {
+            return None;
+        }
+
+        let mut field = Self::new(origin, cell_size, width, height);
+
+        for y in 0..height {
+            for x in 0..width {
+                let pixel = ((height - 1 - y) * width + x) * 4;
+                let channel = |offset: usize| (texture.data[pixel + offset] as f32 / 255.0) * 2.0 - 1.0;
+                field.vectors[y * width + x] = Vec2::new(channel(0), channel(1)) * max_speed;
+            }
+        }
+
+        Some(field)
+    }
+
+    /// Points every reachable cell along the shortest path towards `goal`, avoiding cells marked in `blocked`.
+    /// Blocked cells point towards the nearest free cell so members pushed inside an obstacle find their way out.
+    /// Unreachable cells are left at zero.
+    pub fn toward_goal(origin: Vec2, cell_size: f32, width: usize, height: usize, blocked: &[bool], goal: (usize, usize), speed: f32) -> Self {
+        let mut field = Self::new(origin, cell_size, width, height);
+        let distances = Self::distance_field(width, height, blocked, goal);
+        let clearances = Self::clearance_field(width, height, blocked);
+        let open = vec![false; width * height];
+
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * width + x;
+                let best = if blocked[index] {
+                    Self::neighbors(width, height, &open, x, y)
+                        .filter(|(nx, ny, _)| clearances[ny * width + nx] < clearances[index])
+                        .min_by_key(|(nx, ny, _)| clearances[ny * width + nx])
+                } else if distances[index] == u32::MAX || distances[index] == 0 {
+                    None
+                } else {
+                    Self::neighbors(width, height, blocked, x, y)
+                        .min_by_key(|(nx, ny, _)| distances[ny * width + nx])
+                };
+
+                if let Some((nx, ny, _)) = best {
+                    let direction = Vec2::new(nx as f32 - x as f32, ny as f32 - y as f32);
+                    field.vectors[index] = direction.normalize() * speed;
+                }
+            }
+        }
+
+        field
+    }
+
+    /// Dijkstra distances to `goal` in tenths of a cell, `u32::MAX` where unreachable.
+    pub(crate) fn distance_field(width: usize, height: usize, blocked: &[bool], goal: (usize, usize)) -> Vec<u32> {
+        let mut distances = vec![u32::MAX; width * height];
+
+        if goal.0 < width && goal.1 < height && !blocked[goal.1 * width + goal.0] {
+            distances[goal.1 * width + goal.0] = 0;
+        }
+
+        Self::relax(width, distances, move |x, y| Self::neighbors(width, height, blocked, x, y))
+    }
+
+    /// Distances from every cell to the nearest free cell in tenths of a cell, zero outside of obstacles.
+    pub(crate) fn clearance_field(width: usize, height: usize, blocked: &[bool]) -> Vec<u32> {
+        let distances = blocked.iter().map(|x| if *x { u32::MAX } else { 0 }).collect();
+        let open = vec![false; width * height];
+        let open = &open[..];
+
+        Self::relax(width, distances, move |x, y| Self::neighbors(width, height, open, x, y))
+    }
+
+    /// Dijkstra from every cell whose distance is already known.
+    fn relax<I: Iterator<Item = (usize, usize, u32)>>(width: usize, mut distances: Vec<u32>, neighbors: impl Fn(usize, usize) -> I) -> Vec<u32> {
+        let mut queue: BinaryHeap<_> = distances.iter().enumerate()
+            .filter(|(_, distance)| **distance != u32::MAX)
+            .map(|(index, distance)| Reverse((*distance, index % width, index / width)))
+            .collect();
+
+        while let Some(Reverse((distance, x, y))) = queue.pop() {
+            if distance > distances[y * width + x] {
+                continue;
+            }
+
+            for (nx, ny, cost) in neighbors(x, y) {
+                let next = distance + cost;
+                if next < distances[ny * width + nx] {
+                    distances[ny * width + nx] = next;
+                    queue.push(Reverse((next, nx, ny)));
+                }
+            }
+        }
+
+        distances
+    }
+
+    /// Unblocked neighbors of a cell with their step cost. Diagonals may not cut past blocked corners.
+    fn neighbors(width: usize, height: usize, blocked: &[bool], x: usize, y: usize) -> impl Iterator<Item = (usize, usize, u32)> + '_ {
+        let free = move |x: isize, y: isize| x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height && !blocked[y as usize * width + x as usize];
+        let (x, y) = (x as isize, y as isize);
+
+        NEIGHBOR_OFFSETS.iter()
+            .filter(move |(dx, dy)| free(x + dx, y + dy) && (*dx == 0 || *dy == 0 || (free(x + dx, y) && free(x, y + dy))))
+            .map(move |(dx, dy)| {
+                let cost = if *dx == 0 || *dy == 0 { Self::STRAIGHT_COST } else { Self::DIAGONAL_COST };
+                ((x + dx) as usize, (y + dy) as usize, cost)
+            })
+    }
+
+    pub fn cell_center(&self, x: usize, y: usize) -> Vec2 {
+        self.origin + Vec2::new(x as f32 + 0.5, y as f32 + 0.5) * self.cell_size
+    }
+
+    pub fn cell(&self, position: Vec2) -> Option<(usize, usize)> {
+        let grid = (position - self.origin) / self.cell_size;
+
+        if grid.x < 0.0 || grid.y < 0.0 || grid.x >= self.width as f32 || grid.y >= self.height as f32 {
+            return None;
+        }
+
+        Some((grid.x as usize, grid.y as usize))
+    }
+
+    /// Bilinearly interpolated vector at `position`, or `None` outside of the grid.
+    pub fn sample(&self, position: Vec2) -> Option<Vec2> {
+        self.cell(position)?;
+
+        let grid = (position - self.origin) / self.cell_size - Vec2::splat(0.5);
+        let gx = grid.x.max(0.0).min((self.width - 1) as f32);
+        let gy = grid.y.max(0.0).min((self.height - 1) as f32);
+        let (x0, y0) = (gx as usize, gy as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = (gx - x0 as f32, gy - y0 as f32);
+
+        let at = |x: usize, y: usize| self.vectors[y * self.width + x];
+        let bottom = at(x0, y0) * (1.0 - tx) + at(x1, y0) * tx;
+        let top = at(x0, y1) * (1.0 - tx) + at(x1, y1) * tx;
+
+        Some(bottom * (1.0 - ty) + top * ty)
+    }
+}
+
+/// Builds a `FlowField` on the same entity from an image once it has loaded, see `FlowField::from_image`.
+#[cfg(feature = "render")]
+#[derive(Debug, Clone)]
+pub struct FlowFieldImage {
+    pub image: Handle<Texture>,
+    pub origin: Vec2,
+    pub cell_size: f32,
+    pub max_speed: f32,
+    pub strength: f32,
+    pub flocks: FlockFilter
+}
+
+/// Must be added after `FlockingPlugin`, which adds the `STEERING_STAGE` flow fields are sampled in.
+#[derive(Debug, Clone)]
+pub struct FlowFieldPlugin;
+
+impl FlowFieldPlugin {
+    #[cfg(feature = "render")]
+    fn load_images(commands: &mut Commands, textures: Res<Assets<Texture>>, query: Query<(Entity, &FlowFieldImage)>) {
+        for (entity, image) in query.iter() {
+            if let Some(texture) = textures.get(&image.image) {
+                match FlowField::from_image(texture, image.origin, image.cell_size, image.max_speed) {
+                    Some(mut field) => {
+                        field.strength = image.strength;
+                        field.flocks = image.flocks.clone();
+                        commands.insert_one(entity, field);
+                    },
+                    None => warn!("flow field image is not RGBA8")
+                }
+
+                commands.remove_one::<FlowFieldImage>(entity);
+            }
+        }
+    }
+
+    fn flow_fields(field_query: Query<&FlowField>, mut member_query: Query<(&GlobalTransform, &Velocity, &FlockMemberParams, &mut ExternalSteering, Option<&Parent>), With<FlockMemberMarker>>) {
+        for (transform, velocity, params, mut steering, parent) in member_query.iter_mut() {
+            let position = transform.translation.truncate();
+
+            for field in field_query.iter() {
+                if !field.flocks.affects(parent.map(|x| x.0)) || params.max_speed <= 0.0 {
+                    continue;
+                }
+
+                if let Some(desired) = field.sample(position) {
+                    steering.0 += (desired - velocity.0) / params.max_speed * field.strength;
+                }
+            }
+        }
+    }
+}
+
+impl Plugin for FlowFieldPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        #[cfg(feature = "render")]
+        app.add_system(Self::load_images.system());
+
+        app.add_system_to_stage(STEERING_STAGE, Self::flow_fields.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> FlowField {
+        let mut field = FlowField::new(Vec2::zero(), 10.0, 2, 2);
+        field.vectors = vec![Vec2::zero(), Vec2::new(2.0, 0.0), Vec2::new(0.0, 2.0), Vec2::new(2.0, 2.0)];
+        field
+    }
+
+    #[test]
+    fn samples_interpolate_between_cell_centers() {
+        let field = square();
+
+        assert_eq!(field.sample(Vec2::new(5.0, 5.0)), Some(Vec2::zero()));
+        assert_eq!(field.sample(Vec2::new(15.0, 5.0)), Some(Vec2::new(2.0, 0.0)));
+        assert_eq!(field.sample(Vec2::new(15.0, 15.0)), Some(Vec2::new(2.0, 2.0)));
+        assert_eq!(field.sample(Vec2::new(10.0, 10.0)), Some(Vec2::new(1.0, 1.0)));
+    }
+
+    #[test]
+    fn samples_clamp_at_the_edges() {
+        let field = square();
+
+        assert_eq!(field.sample(Vec2::new(0.0, 0.0)), Some(Vec2::zero()));
+        assert_eq!(field.sample(Vec2::new(19.5, 5.0)), Some(Vec2::new(2.0, 0.0)));
+        assert_eq!(field.sample(Vec2::new(10.0, 19.5)), Some(Vec2::new(1.0, 2.0)));
+    }
+
+    #[test]
+    fn samples_outside_the_grid_are_none() {
+        let field = square();
+
+        assert_eq!(field.sample(Vec2::new(-0.5, 5.0)), None);
+        assert_eq!(field.sample(Vec2::new(5.0, -0.5)), None);
+        assert_eq!(field.sample(Vec2::new(20.0, 5.0)), None);
+        assert_eq!(field.sample(Vec2::new(5.0, 20.0)), None);
+    }
+
+    #[test]
+    fn diagonal_steps_cost_fourteen() {
+        let distances = FlowField::distance_field(3, 3, &[false; 9], (0, 0));
+
+        assert_eq!(distances[1], 10);
+        assert_eq!(distances[4], 14);
+        assert_eq!(distances[5], 24);
+        assert_eq!(distances[8], 28);
+    }
+
+    #[test]
+    fn fields_point_downhill() {
+        let (width, height) = (6, 5);
+        let mut blocked = vec![false; width * height];
+        for y in 0..4 {
+            blocked[y * width + 3] = true;
+        }
+
+        let goal = (5, 0);
+        let distances = FlowField::distance_field(width, height, &blocked, goal);
+        let field = FlowField::toward_goal(Vec2::zero(), 1.0, width, height, &blocked, goal, 1.0);
+
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * width + x;
+                if blocked[index] || (x, y) == goal {
+                    continue;
+                }
+
+                let vector = field.vectors[index];
+                assert!((vector.length() - 1.0).abs() < 1e-5, "cell {:?} has no direction", (x, y));
+
+                let (nx, ny) = ((x as f32 + vector.x).round() as usize, (y as f32 + vector.y).round() as usize);
+                assert!(distances[ny * width + nx] < distances[index], "cell {:?} points uphill", (x, y));
+            }
+        }
+    }
+}
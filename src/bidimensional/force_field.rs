@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+
+use crate::util::*;
+use super::{ ExternalSteering, FlockMemberMarker, STEERING_STAGE, WorldBounds };
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Falloff {
+    Linear,
+    /// Full strength up to `min_distance`, falling off with the inverse square of the distance beyond it.
+    /// A `min_distance` of zero uses `DEFAULT_MIN_FRACTION` of the field radius.
+    InverseSquare { min_distance: f32 },
+    Smoothstep
+}
+
+impl Falloff {
+    /// Fraction of the radius kept at full strength by `InverseSquare` when `min_distance` is zero.
+    pub const DEFAULT_MIN_FRACTION: f32 = 0.25;
+
+    /// Weight in `[0, 1]` of a field at `distance` from its center. Always zero outside `radius`.
+    pub fn weight(&self, distance: f32, radius: f32) -> f32 {
+        if distance >= radius || radius <= 0.0 {
+            return 0.0;
+        }
+
+        let t = distance / radius;
+        match *self {
+            Falloff::Linear => 1.0 - t,
+            Falloff::InverseSquare { min_distance } => {
+                // Measured in units of the field, so the curve is the same for any radius
+                let min_t = if min_distance > 0.0 { min_distance / radius } else { Self::DEFAULT_MIN_FRACTION };
+                let ratio = min_t / t.max(min_t);
+                ratio * ratio
+            },
+            Falloff::Smoothstep => 1.0 - t * t * (3.0 - 2.0 * t)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlockFilter {
+    All,
+    Only(Vec<Entity>),
+    Except(Vec<Entity>)
+}
+
+impl FlockFilter {
+    pub fn affects(&self, flock: Option<Entity>) -> bool {
+        match (self, flock) {
+            (FlockFilter::All, _) => true,
+            (FlockFilter::Only(flocks), Some(flock)) => flocks.contains(&flock),
+            (FlockFilter::Only(_), None) => false,
+            (FlockFilter::Except(flocks), Some(flock)) => !flocks.contains(&flock),
+            (FlockFilter::Except(_), None) => true
+        }
+    }
+}
+
+/// Strength is on the scale of the flocking rules, so 1.0 at the center matches a full strength rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForceField {
+    pub radius: f32,
+    pub strength: f32,
+    pub falloff: Falloff,
+    pub flocks: FlockFilter
+}
+
+impl Default for ForceField {
+    fn default() -> Self {
+        ForceField {
+            radius: 200.0,
+            strength: 1.0,
+            falloff: Falloff::Linear,
+            flocks: FlockFilter::All
+        }
+    }
+}
+
+impl ForceField {
+    /// Steering towards the field's center for a member at `position`, measured across the wrapping `bounds`.
+    pub fn steering(&self, center: Vec2, position: Vec2, flock: Option<Entity>, bounds: Bounds<Vec2>) -> Vec2 {
+        let difference = center.bound_to(position, bounds);
+        let distance = difference.length();
+
+        if distance <= 0.0 || !self.flocks.affects(flock) {
+            return Vec2::zero();
+        }
+
+        difference / distance * self.strength * self.falloff.weight(distance, self.radius)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attractor(pub ForceField);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repulsor(pub ForceField);
+
+/// Must be added after `FlockingPlugin`, which adds the `STEERING_STAGE` fields are applied in.
+#[derive(Debug, Clone)]
+pub struct ForceFieldPlugin;
+
+impl ForceFieldPlugin {
+    fn force_fields(
+        world_bounds: Res<WorldBounds>,
+        attractor_query: Query<(&GlobalTransform, &Attractor)>,
+        repulsor_query: Query<(&GlobalTransform, &Repulsor)>,
+        mut member_query: Query<(&GlobalTransform, &mut ExternalSteering, Option<&Parent>), With<FlockMemberMarker>>
+    ) {
+        let bounds = world_bounds.bounds;
+
+        for (transform, mut steering, parent) in member_query.iter_mut() {
+            let position = transform.translation.truncate();
+            let flock = parent.map(|x| x.0);
+
+            for (field_transform, attractor) in attractor_query.iter() {
+                steering.0 += attractor.0.steering(field_transform.translation.truncate(), position, flock, bounds);
+            }
+
+            for (field_transform, repulsor) in repulsor_query.iter() {
+                steering.0 -= repulsor.0.steering(field_transform.translation.truncate(), position, flock, bounds);
+            }
+        }
+    }
+}
+
+impl Plugin for ForceFieldPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .init_resource::<WorldBounds>()
+            .add_system_to_stage(STEERING_STAGE, Self::force_fields.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_min_distance_scales_with_the_radius() {
+        let falloff = Falloff::InverseSquare { min_distance: 0.0 };
+
+        assert_eq!(falloff.weight(0.0, 200.0), 1.0);
+        assert_eq!(falloff.weight(50.0, 200.0), 1.0);
+        assert!((falloff.weight(100.0, 200.0) - 0.25).abs() < 1e-6);
+        assert!((falloff.weight(10.0, 40.0) - falloff.weight(100.0, 400.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn every_falloff_reaches_half_the_radius() {
+        let falloffs = [
+            Falloff::Linear,
+            Falloff::InverseSquare { min_distance: 0.0 },
+            Falloff::InverseSquare { min_distance: 50.0 },
+            Falloff::Smoothstep
+        ];
+
+        for falloff in falloffs.iter() {
+            let weight = falloff.weight(100.0, 200.0);
+            assert!(weight > 0.1 && weight < 1.0, "{:?} weighs {} at half the radius", falloff, weight);
+            assert_eq!(falloff.weight(200.0, 200.0), 0.0);
+        }
+    }
+
+    #[test]
+    fn fields_reach_across_the_edges() {
+        let bounds = Bounds::new(Vec2::new(-300.0, -300.0), Vec2::new(300.0, 300.0));
+        let field = ForceField::default();
+        let steering = field.steering(Vec2::new(290.0, 0.0), Vec2::new(-290.0, 0.0), None, bounds);
+
+        assert!(steering.x < 0.0);
+        assert_eq!(steering.y, 0.0);
+    }
+}
@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::util::*;
+use super::{ ExternalSteering, FlockMemberMarker, FlockMemberParams, FlockStats, STEERING_STAGE, Velocity, WorldBounds };
+
+/// Slot layouts in the flock's local frame, where +x is the flock's heading and +y is to its left.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FormationShape {
+    /// Abreast, perpendicular to the heading.
+    Line { spacing: f32 },
+    /// A V with the leader at the tip.
+    Wedge { spacing: f32 },
+    /// Single file along the heading.
+    Column { spacing: f32 },
+    Circle { radius: f32 },
+    /// Rows of `columns` members, filled front to back.
+    Grid { columns: usize, spacing: f32 },
+    /// Points used as given, without centering. Members beyond the number of points get no slot.
+    Custom(Vec<Vec2>)
+}
+
+impl FormationShape {
+    /// Local slot offsets for `count` members, centered on the flock's centroid.
+    pub fn slots(&self, count: usize) -> Vec<Vec2> {
+        let mut slots: Vec<Vec2> = match self {
+            FormationShape::Line { spacing } => (0..count)
+                .map(|i| Vec2::new(0.0, i as f32 * spacing))
+                .collect(),
+            FormationShape::Wedge { spacing } => (0..count)
+                .map(|i| {
+                    let rank = ((i + 1) / 2) as f32;
+                    let side = if i % 2 == 1 { 1.0 } else { -1.0 };
+                    Vec2::new(-rank * spacing, side * rank * spacing)
+                })
+                .collect(),
+            FormationShape::Column { spacing } => (0..count)
+                .map(|i| Vec2::new(-(i as f32) * spacing, 0.0))
+                .collect(),
+            FormationShape::Circle { radius } => (0..count)
+                .map(|i| {
+                    let angle = i as f32 / count as f32 * std::f32::consts::PI * 2.0;
+                    Vec2::new(angle.cos(), angle.sin()) * *radius
+                })
+                .collect(),
+            FormationShape::Grid { columns, spacing } => {
+                let columns = (*columns).max(1);
+                (0..count)
+                    .map(|i| Vec2::new(-((i / columns) as f32), (i % columns) as f32) * *spacing)
+                    .collect()
+            },
+            FormationShape::Custom(points) => return points.iter().copied().take(count).collect()
+        };
+
+        if !slots.is_empty() {
+            let center = slots.iter().fold(Vec2::zero(), |sum, x| sum + *x) / slots.len() as f32;
+            for slot in slots.iter_mut() {
+                *slot -= center;
+            }
+        }
+
+        slots
+    }
+}
+
+/// Arranges a `Flock`'s members in `shape`, oriented along the flock's heading. Members steer to their slot
+/// with `strength` on the same scale as the flocking rules, slowing down within `arrival_radius` of it.
+/// Separation still applies, lowering the flock's alignment and cohesion strengths keeps the formation tighter.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Formation {
+    pub shape: FormationShape,
+    pub strength: f32,
+    pub arrival_radius: f32
+}
+
+impl Formation {
+    pub fn new(shape: FormationShape) -> Self {
+        Formation {
+            shape,
+            strength: 1.0,
+            arrival_radius: 50.0
+        }
+    }
+}
+
+/// Slot assignments of a `Formation`, kept between ticks so members only trade places for a clearly shorter total distance.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormationSlots {
+    pub heading: Vec2,
+    assignments: HashMap<Entity, usize>
+}
+
+impl FormationSlots {
+    pub fn slot(&self, member: Entity) -> Option<usize> {
+        self.assignments.get(&member).copied()
+    }
+}
+
+/// Must be added after `FlockingPlugin`, which adds the `STEERING_STAGE` formations run in.
+#[derive(Debug, Clone)]
+pub struct FormationPlugin;
+
+impl FormationPlugin {
+    /// Distances to a member's previous slot are scaled by this, so members only trade places when that is clearly shorter.
+    const HYSTERESIS: f32 = 0.8;
+
+    /// Gives every member the slot minimizing the total distance to the slots, with the previous assignment favored by
+    /// `HYSTERESIS`. The assignment is solved exactly in `O(n³)`, fine for formations up to a few hundred members.
+    fn assign_slots(slots: &mut FormationSlots, members: &[(Entity, Vec2)], targets: &[Vec2], bounds: Bounds<Vec2>) {
+        let cost = |member: usize, slot: usize| {
+            let (entity, position) = members[member];
+            let distance = targets[slot].bound_to(position, bounds).length();
+            if slots.slot(entity) == Some(slot) { distance * Self::HYSTERESIS } else { distance }
+        };
+
+        let mut assignments = HashMap::new();
+        if members.len() <= targets.len() {
+            let costs: Vec<f32> = (0..members.len()).flat_map(|x| (0..targets.len()).map(move |y| (x, y))).map(|(x, y)| cost(x, y)).collect();
+            for (member, slot) in Self::optimal_assignment(&costs, members.len(), targets.len()).into_iter().enumerate() {
+                if let Some(slot) = slot {
+                    assignments.insert(members[member].0, slot);
+                }
+            }
+        } else {
+            // More members than slots, pick which members get one instead
+            let costs: Vec<f32> = (0..targets.len()).flat_map(|x| (0..members.len()).map(move |y| (x, y))).map(|(x, y)| cost(y, x)).collect();
+            for (slot, member) in Self::optimal_assignment(&costs, targets.len(), members.len()).into_iter().enumerate() {
+                if let Some(member) = member {
+                    assignments.insert(members[member].0, slot);
+                }
+            }
+        }
+
+        slots.assignments = assignments;
+    }
+
+    /// Hungarian method over a `rows` by `columns` cost matrix stored row by row, with `rows <= columns`.
+    /// Returns the column given to each row, `None` only when costs are not comparable.
+    fn optimal_assignment(costs: &[f32], rows: usize, columns: usize) -> Vec<Option<usize>> {
+        // Potentials and matches are 1-based, column 0 stands for the row being inserted
+        let mut row_potential = vec![0.0; rows + 1];
+        let mut column_potential = vec![0.0; columns + 1];
+        let mut matched = vec![0; columns + 1];
+        let mut way = vec![0; columns + 1];
+
+        for row in 1..=rows {
+            matched[0] = row;
+            let mut column = 0;
+            let mut min_slack = vec![f32::INFINITY; columns + 1];
+            let mut used = vec![false; columns + 1];
+
+            loop {
+                used[column] = true;
+                let current_row = matched[column];
+                let mut delta = f32::INFINITY;
+                let mut next = 0;
+
+                for j in 1..=columns {
+                    if used[j] {
+                        continue;
+                    }
+
+                    let slack = costs[(current_row - 1) * columns + j - 1] - row_potential[current_row] - column_potential[j];
+                    if slack < min_slack[j] {
+                        min_slack[j] = slack;
+                        way[j] = column;
+                    }
+                    if min_slack[j] < delta {
+                        delta = min_slack[j];
+                        next = j;
+                    }
+                }
+
+                for (j, used) in used.iter().enumerate() {
+                    if *used {
+                        row_potential[matched[j]] += delta;
+                        column_potential[j] -= delta;
+                    } else {
+                        min_slack[j] -= delta;
+                    }
+                }
+
+                column = next;
+                if column == 0 || matched[column] == 0 {
+                    break;
+                }
+            }
+
+            // Flip the augmenting path back to the inserted row
+            while column != 0 {
+                let previous = way[column];
+                matched[column] = matched[previous];
+                column = previous;
+            }
+        }
+
+        let mut assignment = vec![None; rows];
+        for (j, row) in matched.iter().enumerate().skip(1) {
+            if *row != 0 {
+                assignment[row - 1] = Some(j - 1);
+            }
+        }
+
+        assignment
+    }
+
+    fn formations(
+        commands: &mut Commands,
+        world_bounds: Res<WorldBounds>,
+        mut flock_query: Query<(Entity, &Formation, &Children, Option<&FlockStats>, Option<&mut FormationSlots>)>,
+        mut member_query: Query<(&GlobalTransform, &Velocity, &FlockMemberParams, &mut ExternalSteering), With<FlockMemberMarker>>
+    ) {
+        let bounds = world_bounds.bounds;
+
+        for (entity, formation, children, stats, slots) in flock_query.iter_mut() {
+            let (stats, mut slots) = match (stats, slots) {
+                (Some(stats), Some(slots)) => (stats, slots),
+                (_, None) => {
+                    commands.insert_one(entity, FormationSlots::default());
+                    continue;
+                },
+                _ => continue
+            };
+
+            if stats.heading.length_squared() > 0.0 {
+                slots.heading = stats.heading.normalize();
+            } else if slots.heading == Vec2::zero() {
+                slots.heading = Vec2::unit_x();
+            }
+
+            let members: Vec<(Entity, Vec2)> = children.iter()
+                .filter_map(|x| member_query.get_component::<GlobalTransform>(*x).ok().map(|transform| (*x, transform.translation.truncate())))
+                .collect();
+
+            let (forward, left) = (slots.heading, Vec2::new(-slots.heading.y, slots.heading.x));
+            let targets: Vec<Vec2> = formation.shape.slots(members.len()).into_iter()
+                .map(|x| stats.centroid + forward * x.x + left * x.y)
+                .collect();
+
+            Self::assign_slots(&mut slots, &members, &targets, bounds);
+
+            for (member, position) in members {
+                let slot = match slots.slot(member) {
+                    Some(slot) => slot,
+                    None => continue
+                };
+
+                if let Ok((_, velocity, params, mut steering)) = member_query.get_mut(member) {
+                    if params.max_speed <= 0.0 {
+                        continue;
+                    }
+
+                    let difference = targets[slot].bound_to(position, bounds);
+                    let distance = difference.length();
+
+                    let mut desired = stats.heading;
+                    if distance > 0.0 {
+                        desired += difference / distance * params.max_speed * (distance / formation.arrival_radius.max(1.0)).min(1.0);
+                    }
+
+                    if desired.length_squared() > params.max_speed * params.max_speed {
+                        desired = desired.normalize() * params.max_speed;
+                    }
+
+                    steering.0 += (desired - velocity.0) / params.max_speed * formation.strength;
+                }
+            }
+        }
+    }
+}
+
+impl Plugin for FormationPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_to_stage(STEERING_STAGE, Self::formations.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_slots(shape: FormationShape, count: usize, expected: &[Vec2]) {
+        let slots = shape.slots(count);
+
+        assert_eq!(slots.len(), expected.len(), "{:?}", shape);
+        for (slot, expected) in slots.iter().zip(expected.iter()) {
+            assert!((*slot - *expected).length() < 1e-4, "{:?} has a slot at {:?} instead of {:?}", shape, slot, expected);
+        }
+    }
+
+    fn bounds() -> Bounds<Vec2> {
+        Bounds::new(Vec2::new(-500.0, -500.0), Vec2::new(500.0, 500.0))
+    }
+
+    fn members(positions: &[Vec2]) -> Vec<(Entity, Vec2)> {
+        positions.iter().enumerate().map(|(i, x)| (Entity::new(i as u32), *x)).collect()
+    }
+
+    fn total_distance(slots: &FormationSlots, members: &[(Entity, Vec2)], targets: &[Vec2]) -> f32 {
+        members.iter().map(|(entity, position)| (targets[slots.slot(*entity).unwrap()] - *position).length()).sum()
+    }
+
+    #[test]
+    fn shapes_are_centered() {
+        assert_slots(FormationShape::Line { spacing: 10.0 }, 3, &[Vec2::new(0.0, -10.0), Vec2::new(0.0, 0.0), Vec2::new(0.0, 10.0)]);
+        assert_slots(FormationShape::Wedge { spacing: 10.0 }, 3, &[Vec2::new(20.0 / 3.0, 0.0), Vec2::new(-10.0 / 3.0, 10.0), Vec2::new(-10.0 / 3.0, -10.0)]);
+        assert_slots(FormationShape::Column { spacing: 10.0 }, 3, &[Vec2::new(10.0, 0.0), Vec2::new(0.0, 0.0), Vec2::new(-10.0, 0.0)]);
+        assert_slots(FormationShape::Circle { radius: 10.0 }, 4, &[Vec2::new(10.0, 0.0), Vec2::new(0.0, 10.0), Vec2::new(-10.0, 0.0), Vec2::new(0.0, -10.0)]);
+        assert_slots(FormationShape::Grid { columns: 2, spacing: 10.0 }, 4, &[Vec2::new(5.0, -5.0), Vec2::new(5.0, 5.0), Vec2::new(-5.0, -5.0), Vec2::new(-5.0, 5.0)]);
+        assert_slots(FormationShape::Line { spacing: 10.0 }, 0, &[]);
+    }
+
+    #[test]
+    fn custom_shapes_are_used_as_given() {
+        let shape = FormationShape::Custom(vec![Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0)]);
+
+        assert_slots(shape.clone(), 1, &[Vec2::new(1.0, 2.0)]);
+        assert_slots(shape, 3, &[Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0)]);
+    }
+
+    #[test]
+    fn assignments_minimize_the_total_distance() {
+        let members = members(&[Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0), Vec2::new(5.0, 5.0), Vec2::new(-3.0, 1.0)]);
+        let targets = [Vec2::new(1.0, 0.0), Vec2::new(-1.0, 0.0), Vec2::new(-3.0, 0.0), Vec2::new(4.0, 4.0)];
+        let mut slots = FormationSlots::default();
+
+        FormationPlugin::assign_slots(&mut slots, &members, &targets, bounds());
+
+        // Taking the nearest free slot in order would send the first member to (1, 0) and the second the long way around
+        assert_eq!(slots.slot(members[0].0), Some(1));
+        assert_eq!(slots.slot(members[1].0), Some(0));
+        assert_eq!(slots.slot(members[2].0), Some(3));
+        assert_eq!(slots.slot(members[3].0), Some(2));
+        assert!((total_distance(&slots, &members, &targets) - (1.0 + 1.0 + 2.0f32.sqrt() + 1.0)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn assignments_hold_over_ticks() {
+        let targets = [Vec2::new(-10.0, 0.0), Vec2::new(10.0, 0.0)];
+        let mut slots = FormationSlots::default();
+
+        // The members drift past each other, a swap ends up shorter but not by enough to trade places
+        for tick in 0..10 {
+            let offset = tick as f32 * 0.2;
+            let members = members(&[Vec2::new(1.0 - offset, 0.0), Vec2::new(-1.0 + offset, 0.0)]);
+            FormationPlugin::assign_slots(&mut slots, &members, &targets, bounds());
+
+            assert_eq!(slots.slot(members[0].0), Some(1), "tick {}", tick);
+            assert_eq!(slots.slot(members[1].0), Some(0), "tick {}", tick);
+        }
+    }
+
+    #[test]
+    fn extra_members_get_no_slot() {
+        let members = members(&[Vec2::new(0.0, 0.0), Vec2::new(50.0, 0.0), Vec2::new(1.0, 0.0)]);
+        let targets = [Vec2::new(0.0, 0.0), Vec2::new(2.0, 0.0)];
+        let mut slots = FormationSlots::default();
+
+        FormationPlugin::assign_slots(&mut slots, &members, &targets, bounds());
+
+        assert_eq!(slots.slot(members[0].0), Some(0));
+        assert_eq!(slots.slot(members[1].0), None);
+        assert_eq!(slots.slot(members[2].0), Some(1));
+    }
+}
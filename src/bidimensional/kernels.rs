@@ -0,0 +1,254 @@
+use std::ops::Range;
+
+use bevy::prelude::*;
+
+use crate::util::*;
+use super::{ Flock, FlockMemberParams, FlockSteering, FlockingPlugin };
+
+/// Structure-of-arrays copy of a flock's members, taken before any of them is steered.
+/// `offsets` are the positions the flocking rules see, wrapped relative to `average_position`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FlockSnapshot {
+    pub ids: Vec<u32>,
+    pub positions_x: Vec<f32>,
+    pub positions_y: Vec<f32>,
+    pub offsets_x: Vec<f32>,
+    pub offsets_y: Vec<f32>,
+    pub velocities: Vec<Vec2>,
+    pub external: Vec<Vec2>,
+    pub safe_radii: Vec<f32>,
+    pub max_speeds: Vec<f32>,
+    pub max_accels: Vec<f32>,
+    pub average_position: Vec2,
+    pub average_forward: Vec2
+}
+
+impl FlockSnapshot {
+    pub fn with_capacity(capacity: usize) -> Self {
+        FlockSnapshot {
+            ids: Vec::with_capacity(capacity),
+            positions_x: Vec::with_capacity(capacity),
+            positions_y: Vec::with_capacity(capacity),
+            offsets_x: Vec::with_capacity(capacity),
+            offsets_y: Vec::with_capacity(capacity),
+            velocities: Vec::with_capacity(capacity),
+            external: Vec::with_capacity(capacity),
+            safe_radii: Vec::with_capacity(capacity),
+            max_speeds: Vec::with_capacity(capacity),
+            max_accels: Vec::with_capacity(capacity),
+            ..Default::default()
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn push(&mut self, id: u32, position: Vec2, velocity: Vec2, external: Vec2, params: &FlockMemberParams) {
+        self.ids.push(id);
+        self.positions_x.push(position.x);
+        self.positions_y.push(position.y);
+        self.velocities.push(velocity);
+        self.external.push(external);
+        self.safe_radii.push(params.safe_radius);
+        self.max_speeds.push(params.max_speed);
+        self.max_accels.push(params.max_accel);
+    }
+
+    pub fn position(&self, index: usize) -> Vec2 {
+        Vec2::new(self.positions_x[index], self.positions_y[index])
+    }
+
+    pub fn offset(&self, index: usize) -> Vec2 {
+        Vec2::new(self.offsets_x[index], self.offsets_y[index])
+    }
+
+    /// Sets the flock averages and wraps every position relative to them. Must be called once all members are pushed.
+    pub fn set_averages(&mut self, average_position: Vec2, average_forward: Vec2, bounds: Bounds<Vec2>) {
+        self.average_position = average_position;
+        self.average_forward = average_forward;
+        self.offsets_x.clear();
+        self.offsets_y.clear();
+
+        for index in 0..self.len() {
+            let offset = self.position(index).bound_to(average_position, bounds);
+            self.offsets_x.push(offset.x);
+            self.offsets_y.push(offset.y);
+        }
+    }
+
+    /// Separation of the members in `range` against every member, matching `FlockingPlugin::calculate_separation`.
+    /// The inner loop is branch free over plain slices so it can be vectorized.
+    pub fn separation(&self, range: Range<usize>, out: &mut [Vec2]) {
+        let others = self.positions_x.iter()
+            .zip(self.positions_y.iter())
+            .zip(self.safe_radii.iter())
+            .zip(self.ids.iter());
+
+        for (separation, index) in out.iter_mut().zip(range) {
+            let (x, y, safe_radius, id) = (self.offsets_x[index], self.offsets_y[index], self.safe_radii[index], self.ids[index]);
+            let (mut sum_x, mut sum_y) = (0.0f32, 0.0f32);
+
+            for (((other_x, other_y), other_radius), other_id) in others.clone() {
+                let (dx, dy) = (x - other_x, y - other_y);
+                let distance_squared = dx * dx + dy * dy;
+                let minimum_distance = safe_radius + other_radius;
+                let overlapping = *other_id != id && distance_squared < minimum_distance * minimum_distance;
+
+                let distance = distance_squared.sqrt();
+                let weight = if overlapping { (minimum_distance - distance) / (minimum_distance * distance) } else { 0.0 };
+
+                sum_x += dx * weight;
+                sum_y += dy * weight;
+            }
+
+            *separation = Vec2::new(sum_x, sum_y);
+            if separation.length_squared() > 1.0 {
+                *separation = separation.normalize();
+            }
+        }
+    }
+
+    /// New velocities and steering contributions of the members in `range`. Only reads the snapshot,
+    /// so ranges can be steered in any order and on any thread with the same result.
+    pub fn steer(&self, flock: &Flock, range: Range<usize>, delta_seconds: f32) -> Vec<(Vec2, FlockSteering)> {
+        let mut separations = vec![Vec2::zero(); range.len()];
+        self.separation(range.clone(), &mut separations);
+
+        range.zip(separations.into_iter())
+            .map(|(index, separation)| {
+                let max_speed = self.max_speeds[index];
+                let max_accel = self.max_accels[index];
+
+                let alignment = flock.alignment_strength * FlockingPlugin::calculate_alignment(max_speed, self.average_forward);
+                let cohesion = flock.cohesion_strength * FlockingPlugin::calculate_cohesion(self.offset(index), self.average_position, flock.flock_radius);
+                let separation = flock.separation_strength * separation;
+
+                let mut acceleration: Vec2 = max_speed * (alignment + cohesion + separation + self.external[index]);
+
+                if acceleration.length_squared() > max_accel * max_accel {
+                    acceleration = acceleration.normalize() * max_accel;
+                }
+
+                let mut velocity = self.velocities[index] + acceleration * delta_seconds;
+
+                if velocity.length_squared() > max_speed + max_speed {
+                    velocity = velocity.normalize() * max_speed;
+                }
+
+                (velocity, FlockSteering {
+                    alignment: max_speed * alignment,
+                    cohesion: max_speed * cohesion,
+                    separation: max_speed * separation
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::{ *, super::SimRng };
+
+    const TOLERANCE: f32 = 1e-3;
+
+    fn window_bounds() -> Bounds<Vec2> {
+        Bounds::new(Vec2::new(-400.0, -300.0), Vec2::new(400.0, 300.0))
+    }
+
+    fn random_flock(count: usize, seed: u64) -> Vec<(u32, Vec2, Vec2, FlockMemberParams)> {
+        let mut rng = SimRng::new(seed).stream("random_flock");
+
+        (0..count as u32)
+            .map(|id| (
+                id,
+                Vec2::new(rng.gen_range(-400.0..400.0), rng.gen_range(-300.0..300.0)),
+                Vec2::new(rng.gen_range(-200.0..200.0), rng.gen_range(-200.0..200.0)),
+                FlockMemberParams {
+                    max_speed: rng.gen_range(100.0..300.0),
+                    max_accel: rng.gen_range(50.0..150.0),
+                    safe_radius: rng.gen_range(10.0..60.0)
+                }
+            ))
+            .collect()
+    }
+
+    #[test]
+    fn kernels_match_scalar_steering() {
+        let bounds = window_bounds();
+        let flock = Flock {
+            flock_radius: 50.0,
+            alignment_strength: 1.0,
+            cohesion_strength: 0.8,
+            separation_strength: 1.2
+        };
+
+        for seed in 0..8 {
+            let members = random_flock(200, seed);
+            let boids: Vec<(u32, Vec2, FlockMemberParams)> = members.iter().map(|(id, position, _, params)| (*id, *position, *params)).collect();
+
+            let average_position = members.iter().fold(Vec2::zero(), |sum, x| sum + x.1) / members.len() as f32;
+            let average_forward = members.iter().fold(Vec2::zero(), |sum, x| sum + x.2) / members.len() as f32;
+
+            let mut snapshot = FlockSnapshot::with_capacity(members.len());
+            for (id, position, velocity, params) in members.iter() {
+                snapshot.push(*id, *position, *velocity, Vec2::zero(), params);
+            }
+            snapshot.set_averages(average_position, average_forward, bounds);
+
+            let steered = snapshot.steer(&flock, 0..members.len(), 1.0 / 60.0);
+
+            for ((id, position, velocity, params), (new_velocity, steering)) in members.iter().zip(steered.iter()) {
+                let position = position.bound_to(average_position, bounds);
+
+                let alignment = flock.alignment_strength * FlockingPlugin::calculate_alignment(params.max_speed, average_forward);
+                let cohesion = flock.cohesion_strength * FlockingPlugin::calculate_cohesion(position, average_position, flock.flock_radius);
+                let separation = flock.separation_strength * FlockingPlugin::calculate_separation(*id, params, position, &boids);
+
+                let mut acceleration = params.max_speed * (alignment + cohesion + separation);
+                if acceleration.length_squared() > params.max_accel * params.max_accel {
+                    acceleration = acceleration.normalize() * params.max_accel;
+                }
+
+                let mut expected = *velocity + acceleration / 60.0;
+                if expected.length_squared() > params.max_speed + params.max_speed {
+                    expected = expected.normalize() * params.max_speed;
+                }
+
+                assert!((steering.separation - params.max_speed * separation).length() <= TOLERANCE * params.max_speed, "separation of {} differs", id);
+                assert!((*new_velocity - expected).length() <= TOLERANCE * params.max_speed, "velocity of {} differs", id);
+            }
+        }
+    }
+
+    #[test]
+    fn steering_does_not_depend_on_batches() {
+        let bounds = window_bounds();
+        let flock = Flock {
+            flock_radius: 50.0,
+            alignment_strength: 1.0,
+            cohesion_strength: 1.0,
+            separation_strength: 1.0
+        };
+
+        let members = random_flock(300, 42);
+        let mut snapshot = FlockSnapshot::with_capacity(members.len());
+        for (id, position, velocity, params) in members.iter() {
+            snapshot.push(*id, *position, *velocity, Vec2::zero(), params);
+        }
+        snapshot.set_averages(Vec2::zero(), Vec2::zero(), bounds);
+
+        let whole = snapshot.steer(&flock, 0..members.len(), 1.0 / 60.0);
+        let batched: Vec<(Vec2, FlockSteering)> = (0..members.len()).step_by(64)
+            .flat_map(|start| snapshot.steer(&flock, start..(start + 64).min(members.len()), 1.0 / 60.0))
+            .collect();
+
+        assert_eq!(whole, batched);
+    }
+}
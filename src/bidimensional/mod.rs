@@ -0,0 +1,33 @@
+mod clock;
+mod movement;
+mod flock;
+mod clustering;
+mod collision;
+mod flow_field;
+mod force_field;
+mod formation;
+mod kernels;
+mod navigation;
+#[cfg(feature = "presets")]
+mod preset;
+mod rng;
+mod scene;
+mod spawner;
+mod world_bounds;
+
+pub use clock::SimulationClock;
+pub use movement::*;
+pub use flock::*;
+pub use clustering::*;
+pub use collision::*;
+pub use flow_field::*;
+pub use force_field::*;
+pub use formation::*;
+pub use kernels::*;
+pub use navigation::*;
+#[cfg(feature = "presets")]
+pub use preset::*;
+pub use rng::SimRng;
+pub use scene::*;
+pub use spawner::*;
+pub use world_bounds::WorldBounds;
\ No newline at end of file
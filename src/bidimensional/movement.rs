@@ -1,5 +1,7 @@
 use bevy::prelude::*;
 
+use super::{ SimulationClock, WorldBounds, clock::simulation_clock, world_bounds::world_bounds };
+
 pub const MOVEMENT_STAGE: &'static str = "MOVEMENT";
 
 #[derive(Debug, Default, PartialEq, Clone, Copy, Reflect)]
@@ -21,10 +23,10 @@ impl Into<Vec2> for Velocity {
 #[derive(Clone, Debug)]
 pub struct MovementPlugin;
 
-fn movement(time: Res<Time>, mut query: Query<(&mut GlobalTransform, &Velocity)>) {
+fn movement(clock: Res<SimulationClock>, mut query: Query<(&mut GlobalTransform, &Velocity)>) {
     for (mut transform, velocity) in query.iter_mut() {
         let old_position = transform.translation;
-        transform.translation += (velocity.0 * time.delta_seconds()).extend(0.0);
+        transform.translation += (velocity.0 * clock.delta_seconds()).extend(0.0);
 
         if transform.translation.x.is_nan() {
             if old_position.x.is_nan() {
@@ -64,6 +66,11 @@ fn movement(time: Res<Time>, mut query: Query<(&mut GlobalTransform, &Velocity)>
 impl Plugin for MovementPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app
+            .register_type::<Velocity>()
+            .init_resource::<SimulationClock>()
+            .init_resource::<WorldBounds>()
+            .add_system_to_stage(stage::PRE_UPDATE, simulation_clock.system())
+            .add_system_to_stage(stage::PRE_UPDATE, world_bounds.system())
             .add_stage_after(stage::POST_UPDATE, MOVEMENT_STAGE, SystemStage::serial())
             .add_system_to_stage(MOVEMENT_STAGE, movement.system());
     }
@@ -0,0 +1,269 @@
+use bevy::prelude::*;
+
+use super::{ ExternalSteering, FlowField, FlockMemberMarker, FlockMemberParams, STEERING_STAGE, Velocity, WorldBounds };
+
+/// Runs after `PRE_UPDATE`, where the `NavigationGrid` is rebuilt, so paths always see the current grid.
+pub const NAVIGATION_STAGE: &'static str = "NAVIGATION";
+
+/// Blocks every navigation cell whose center lies inside the shape, centered on the entity's `GlobalTransform`.
+/// Rectangles are axis aligned, rotation is ignored.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Obstacle {
+    Circle { radius: f32 },
+    Rect { half_extents: Vec2 }
+}
+
+impl Obstacle {
+    /// Whether `point` is inside the shape grown by `margin` on every side.
+    pub fn contains(&self, center: Vec2, point: Vec2, margin: f32) -> bool {
+        let offset = point - center;
+
+        match *self {
+            Obstacle::Circle { radius } => offset.length_squared() <= (radius + margin) * (radius + margin),
+            Obstacle::Rect { half_extents } => offset.x.abs() <= half_extents.x + margin && offset.y.abs() <= half_extents.y + margin
+        }
+    }
+}
+
+/// Where a `Flock` should find its way to. Its members steer along the shortest path around obstacles,
+/// with `strength` on the same scale as the flocking rules.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlockGoal {
+    pub target: Vec2,
+    pub strength: f32
+}
+
+/// The path towards a `FlockGoal`, kept on the flock and recomputed whenever the goal cell or the grid changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlockPath {
+    pub goal_cell: (usize, usize),
+    pub revision: u32,
+    pub field: FlowField
+}
+
+/// `margin` grows every obstacle so members passing by keep some distance from it.
+#[derive(Debug, Clone)]
+pub struct NavigationConfig {
+    pub cell_size: f32,
+    pub margin: f32
+}
+
+impl Default for NavigationConfig {
+    fn default() -> Self {
+        NavigationConfig {
+            cell_size: 20.0,
+            margin: 10.0
+        }
+    }
+}
+
+/// Obstacle occupancy over the `WorldBounds`, rebuilt every tick. `revision` only changes when the occupancy does.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NavigationGrid {
+    pub origin: Vec2,
+    pub cell_size: f32,
+    pub width: usize,
+    pub height: usize,
+    pub blocked: Vec<bool>,
+    pub revision: u32
+}
+
+impl NavigationGrid {
+    pub fn cell(&self, position: Vec2) -> Option<(usize, usize)> {
+        let grid = (position - self.origin) / self.cell_size;
+
+        if grid.x < 0.0 || grid.y < 0.0 || grid.x >= self.width as f32 || grid.y >= self.height as f32 {
+            return None;
+        }
+
+        Some((grid.x as usize, grid.y as usize))
+    }
+
+    pub fn is_blocked(&self, position: Vec2) -> bool {
+        self.cell(position)
+            .map(|(x, y)| self.blocked[y * self.width + x])
+            .unwrap_or(false)
+    }
+}
+
+/// Must be added after `FlockingPlugin`, which adds the `STEERING_STAGE` members follow their paths in.
+#[derive(Clone, Debug, Default)]
+pub struct NavigationPlugin(NavigationConfig);
+
+impl NavigationPlugin {
+    pub fn new(config: NavigationConfig) -> Self {
+        Self(config)
+    }
+
+    fn navigation_grid(config: Res<NavigationConfig>, world_bounds: Res<WorldBounds>, mut grid: ResMut<NavigationGrid>, query: Query<(&GlobalTransform, &Obstacle)>) {
+        let (lower, upper) = (world_bounds.bounds.lower(), world_bounds.bounds.upper());
+        let size = upper - lower;
+
+        let cell_size = config.cell_size.max(1.0);
+        let width = (size.x / cell_size).ceil() as usize;
+        let height = (size.y / cell_size).ceil() as usize;
+        let origin = (lower + upper) / 2.0 - Vec2::new(width as f32, height as f32) * cell_size / 2.0;
+
+        let mut blocked = vec![false; width * height];
+        for (transform, obstacle) in query.iter() {
+            let center = transform.translation.truncate();
+
+            for y in 0..height {
+                for x in 0..width {
+                    let cell_center = origin + Vec2::new(x as f32 + 0.5, y as f32 + 0.5) * cell_size;
+                    if obstacle.contains(center, cell_center, config.margin) {
+                        blocked[y * width + x] = true;
+                    }
+                }
+            }
+        }
+
+        if grid.origin != origin || grid.cell_size != cell_size || grid.width != width || grid.height != height || grid.blocked != blocked {
+            *grid = NavigationGrid {
+                origin,
+                cell_size,
+                width,
+                height,
+                blocked,
+                revision: grid.revision.wrapping_add(1)
+            };
+        }
+    }
+
+    fn flock_paths(commands: &mut Commands, grid: Res<NavigationGrid>, mut query: Query<(Entity, &FlockGoal, Option<&mut FlockPath>)>) {
+        for (entity, goal, path) in query.iter_mut() {
+            let goal_cell = match grid.cell(goal.target) {
+                Some(cell) => cell,
+                None => {
+                    if path.is_some() {
+                        commands.remove_one::<FlockPath>(entity);
+                    }
+                    continue;
+                }
+            };
+
+            if let Some(path) = &path {
+                if path.goal_cell == goal_cell && path.revision == grid.revision {
+                    continue;
+                }
+            }
+
+            let new_path = FlockPath {
+                goal_cell,
+                revision: grid.revision,
+                field: FlowField::toward_goal(grid.origin, grid.cell_size, grid.width, grid.height, &grid.blocked, goal_cell, 1.0)
+            };
+
+            match path {
+                Some(mut path) => *path = new_path,
+                None => { commands.insert_one(entity, new_path); }
+            }
+        }
+    }
+
+    fn path_steering(flock_query: Query<(&FlockGoal, &FlockPath, &Children)>, mut member_query: Query<(&GlobalTransform, &Velocity, &FlockMemberParams, &mut ExternalSteering), With<FlockMemberMarker>>) {
+        for (goal, path, children) in flock_query.iter() {
+            for child in children.iter() {
+                if let Ok((transform, velocity, params, mut steering)) = member_query.get_mut(*child) {
+                    if params.max_speed <= 0.0 {
+                        continue;
+                    }
+
+                    let position = transform.translation.truncate();
+                    let direction = match (path.field.cell(position), path.field.sample(position)) {
+                        // Within the goal cell the field is zero, head straight for the target instead
+                        (Some(cell), _) if cell == path.goal_cell => {
+                            let difference = goal.target - position;
+                            (difference / path.field.cell_size).min(Vec2::one()).max(-Vec2::one())
+                        },
+                        // Cells cut off from the goal are left at zero, leave those members to the other rules
+                        (Some((x, y)), Some(direction)) if path.field.vectors[y * path.field.width + x] != Vec2::zero() => direction,
+                        _ => continue
+                    };
+
+                    steering.0 += (direction * params.max_speed - velocity.0) / params.max_speed * goal.strength;
+                }
+            }
+        }
+    }
+}
+
+impl Plugin for NavigationPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_resource(self.0.clone())
+            .init_resource::<NavigationGrid>()
+            .add_stage_after(stage::PRE_UPDATE, NAVIGATION_STAGE, SystemStage::serial())
+            .add_system_to_stage(stage::PRE_UPDATE, Self::navigation_grid.system())
+            .add_system_to_stage(NAVIGATION_STAGE, Self::flock_paths.system())
+            .add_system_to_stage(STEERING_STAGE, Self::path_steering.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(width: usize, height: usize, walls: &[(usize, usize)]) -> Vec<bool> {
+        let mut blocked = vec![false; width * height];
+        for (x, y) in walls.iter() {
+            blocked[y * width + x] = true;
+        }
+        blocked
+    }
+
+    #[test]
+    fn corridors_cost_ten_per_cell() {
+        let distances = FlowField::distance_field(5, 1, &grid(5, 1, &[]), (0, 0));
+
+        assert_eq!(distances, vec![0, 10, 20, 30, 40]);
+    }
+
+    #[test]
+    fn diagonals_cost_fourteen_without_cutting_corners() {
+        let open = FlowField::distance_field(3, 3, &grid(3, 3, &[]), (0, 0));
+        let corner = FlowField::distance_field(3, 3, &grid(3, 3, &[(1, 0)]), (0, 0));
+
+        assert_eq!(open[1 * 3 + 1], 14);
+        assert_eq!(open[2 * 3 + 2], 28);
+        assert_eq!(corner[1 * 3 + 1], 20);
+    }
+
+    #[test]
+    fn paths_go_around_obstacles() {
+        let blocked = grid(5, 5, &[(2, 0), (2, 1), (2, 2), (2, 3)]);
+        let distances = FlowField::distance_field(5, 5, &blocked, (0, 0));
+        let field = FlowField::toward_goal(Vec2::zero(), 1.0, 5, 5, &blocked, (0, 0), 1.0);
+
+        assert_eq!(distances[4], 108);
+        assert_eq!(distances[4 * 5 + 2], 54);
+        assert!(field.vectors[4].y > 0.0);
+    }
+
+    #[test]
+    fn unreachable_goals_leave_the_field_empty() {
+        let blocked = grid(5, 3, &[(2, 0), (2, 1), (2, 2)]);
+        let distances = FlowField::distance_field(5, 3, &blocked, (0, 0));
+        let field = FlowField::toward_goal(Vec2::zero(), 1.0, 5, 3, &blocked, (0, 0), 1.0);
+
+        for y in 0..3 {
+            for x in 3..5 {
+                assert_eq!(distances[y * 5 + x], u32::MAX);
+                assert_eq!(field.vectors[y * 5 + x], Vec2::zero());
+            }
+        }
+        assert!(FlowField::distance_field(5, 3, &blocked, (2, 1)).iter().all(|x| *x == u32::MAX));
+    }
+
+    #[test]
+    fn blocked_cells_point_out_of_the_obstacle() {
+        let blocked = grid(5, 5, &[(1, 1), (2, 1), (3, 1), (1, 2), (2, 2), (3, 2), (1, 3), (2, 3), (3, 3)]);
+        let field = FlowField::toward_goal(Vec2::zero(), 1.0, 5, 5, &blocked, (0, 0), 1.0);
+
+        assert_eq!(field.vectors[2 * 5 + 1], Vec2::new(-1.0, 0.0));
+        assert_eq!(field.vectors[1 * 5 + 2], Vec2::new(0.0, -1.0));
+        assert_eq!(field.vectors[1 * 5 + 1], Vec2::new(-1.0, 0.0));
+        // The center is equally far from every side, it only has to lead somewhere shallower
+        assert_ne!(field.vectors[2 * 5 + 2], Vec2::zero());
+    }
+}
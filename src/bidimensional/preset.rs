@@ -0,0 +1,139 @@
+use bevy::{
+    prelude::*,
+    asset::{ AssetLoader, LoadContext, LoadedAsset },
+    reflect::TypeUuid,
+    utils::BoxedFuture
+};
+use serde::Deserialize;
+
+use super::{ Flock, FlockClusteringEvent };
+
+/// Flock tuning loaded from a `.flock` file written in RON or TOML. A `Flock` entity holding a `Handle<FlockPreset>`
+/// takes its values from the preset whenever it is loaded or modified.
+#[derive(Debug, Default, PartialEq, Clone, Copy, Deserialize, TypeUuid)]
+#[uuid = "5c0f2b3e-8f41-4a6e-9d2c-3b7a1e6f0d94"]
+pub struct FlockPreset {
+    pub flock_radius: f32,
+    pub alignment_strength: f32,
+    pub cohesion_strength: f32,
+    pub separation_strength: f32
+}
+
+impl FlockPreset {
+    /// Bevy only matches the last extension of an asset path, so RON and TOML presets share `.flock`
+    /// and are told apart by trying RON first.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        match ron::de::from_bytes(bytes) {
+            Ok(preset) => Ok(preset),
+            Err(ron_error) => toml::from_slice(bytes)
+                .map_err(|toml_error| anyhow::anyhow!("flock preset is neither RON ({}) nor TOML ({})", ron_error, toml_error))
+        }
+    }
+}
+
+impl From<&FlockPreset> for Flock {
+    fn from(preset: &FlockPreset) -> Flock {
+        Flock {
+            flock_radius: preset.flock_radius,
+            alignment_strength: preset.alignment_strength,
+            cohesion_strength: preset.cohesion_strength,
+            separation_strength: preset.separation_strength
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FlockPresetLoader;
+
+impl AssetLoader for FlockPresetLoader {
+    fn load<'a>(&'a self, bytes: &'a [u8], load_context: &'a mut LoadContext) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let preset = FlockPreset::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(preset));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["flock"]
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FlockPresetPlugin;
+
+impl FlockPresetPlugin {
+    fn apply_new_presets(presets: Res<Assets<FlockPreset>>, mut query: Query<(&Handle<FlockPreset>, &mut Flock), Added<Handle<FlockPreset>>>) {
+        for (handle, mut flock) in query.iter_mut() {
+            if let Some(preset) = presets.get(handle) {
+                *flock = preset.into();
+            }
+        }
+    }
+
+    fn inherit_split_presets(commands: &mut Commands, mut reader: Local<EventReader<FlockClusteringEvent>>, events: Res<Events<FlockClusteringEvent>>, query: Query<&Handle<FlockPreset>>) {
+        for event in reader.iter(&events) {
+            if let FlockClusteringEvent::Split { from, into } = event {
+                if let Ok(handle) = query.get(*from) {
+                    commands.insert_one(*into, handle.clone());
+                }
+            }
+        }
+    }
+
+    fn apply_changed_presets(mut reader: Local<EventReader<AssetEvent<FlockPreset>>>, events: Res<Events<AssetEvent<FlockPreset>>>, presets: Res<Assets<FlockPreset>>, mut query: Query<(&Handle<FlockPreset>, &mut Flock)>) {
+        for event in reader.iter(&events) {
+            let changed = match event {
+                AssetEvent::Created { handle } | AssetEvent::Modified { handle } => handle,
+                AssetEvent::Removed { .. } => continue
+            };
+
+            if let Some(preset) = presets.get(changed) {
+                for (handle, mut flock) in query.iter_mut() {
+                    if handle == changed {
+                        *flock = preset.into();
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Plugin for FlockPresetPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_asset::<FlockPreset>()
+            .init_asset_loader::<FlockPresetLoader>()
+            .init_resource::<Events<FlockClusteringEvent>>()
+            .add_system(Self::apply_new_presets.system())
+            .add_system(Self::inherit_split_presets.system())
+            .add_system(Self::apply_changed_presets.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shipped_preset_parses() {
+        let preset = FlockPreset::from_bytes(include_bytes!("../../assets/presets/default.flock")).unwrap();
+
+        assert_eq!(preset.flock_radius, 50.0);
+        assert_eq!(preset.separation_strength, 1.0);
+    }
+
+    #[test]
+    fn toml_presets_parse() {
+        let toml = "flock_radius = 80.0\nalignment_strength = 0.5\ncohesion_strength = 0.25\nseparation_strength = 2.0\n";
+        let preset = FlockPreset::from_bytes(toml.as_bytes()).unwrap();
+
+        assert_eq!(preset, FlockPreset {
+            flock_radius: 80.0,
+            alignment_strength: 0.5,
+            cohesion_strength: 0.25,
+            separation_strength: 2.0
+        });
+        assert!(FlockPreset::from_bytes(b"flock_radius: [").is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::prelude::*;
+use rand::{ Error, RngCore, SeedableRng };
+use rand_chacha::ChaCha8Rng;
+
+/// Source of all randomness in the simulation. ChaCha8 produces the same numbers on every platform,
+/// so a run is reproduced from its seed on native and wasm alike.
+///
+/// Systems should draw from a named `stream` rather than the resource itself, so what one system draws
+/// does not shift the numbers another one sees.
+#[derive(Debug, Clone)]
+pub struct SimRng {
+    seed: u64,
+    rng: ChaCha8Rng
+}
+
+impl Default for SimRng {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        SimRng {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed)
+        }
+    }
+
+    /// Seeded randomly, `seed` tells how to reproduce the run.
+    pub fn from_entropy() -> Self {
+        Self::new(rand::random())
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// FNV-1a, unlike `DefaultHasher` it is guaranteed to stay the same across platforms and Rust versions.
+    fn hash(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3))
+    }
+
+    /// An independent stream for `name`, the same for every call with the same seed.
+    pub fn stream(&self, name: &str) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        rng.set_stream(Self::hash(name.as_bytes()));
+        rng
+    }
+
+    /// An independent stream for `name` and `entity`, so entities draw the same numbers regardless of
+    /// the order they are processed in.
+    pub fn entity_stream(&self, name: &str, entity: Entity) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed ^ entity.to_bits().wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        rng.set_stream(Self::hash(name.as_bytes()));
+        rng
+    }
+}
+
+impl RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
@@ -0,0 +1,133 @@
+use std::{ any::type_name, path::PathBuf };
+
+use bevy::{
+    prelude::*,
+    reflect::TypeRegistryArc,
+    scene::{ DynamicScene, SceneSpawner }
+};
+
+use super::{ Flock, FlockMemberMarker, FlockMemberParams, Velocity };
+
+/// `Save` writes the current flocks to a file path, `Load` spawns a scene from an asset path. Scenes are RON
+/// files with Bevy's `.scn` extension, the only one its scene loader claims.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlockSceneCommand {
+    Save(PathBuf),
+    Load(String)
+}
+
+#[derive(Default)]
+struct FlockSceneState {
+    reader: EventReader<FlockSceneCommand>
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FlockScenePlugin;
+
+impl FlockScenePlugin {
+    /// Builds a scene holding only flocks and their members, with only the components needed to restore them.
+    /// `Children` is left out on purpose, since it is rebuilt from `Parent` when the scene is spawned.
+    pub fn flock_scene(world: &World, type_registry: &TypeRegistryArc) -> DynamicScene {
+        let roots = [type_name::<Flock>(), type_name::<FlockMemberMarker>()];
+        let components = [
+            type_name::<Flock>(),
+            type_name::<FlockMemberMarker>(),
+            type_name::<FlockMemberParams>(),
+            type_name::<Velocity>(),
+            type_name::<Transform>(),
+            type_name::<GlobalTransform>(),
+            type_name::<Parent>()
+        ];
+
+        let mut scene = DynamicScene::from_world(world, type_registry);
+        scene.entities.retain(|entity| entity.components.iter().any(|x| roots.contains(&x.type_name())));
+
+        for entity in scene.entities.iter_mut() {
+            entity.components.retain(|x| components.contains(&x.type_name()));
+        }
+
+        scene
+    }
+
+    fn scene_commands(world: &mut World, resources: &mut Resources) {
+        let commands: Vec<FlockSceneCommand> = {
+            let events = resources.get::<Events<FlockSceneCommand>>().unwrap();
+            let mut state = resources.get_mut::<FlockSceneState>().unwrap();
+            state.reader.iter(&events).cloned().collect()
+        };
+
+        for command in commands {
+            match command {
+                FlockSceneCommand::Save(path) => {
+                    let type_registry = resources.get::<TypeRegistryArc>().unwrap();
+                    let scene = Self::flock_scene(world, &type_registry);
+
+                    match scene.serialize_ron(&type_registry) {
+                        Ok(ron) => if let Err(error) = std::fs::write(&path, ron) {
+                            warn!("could not write flock scene {:?}: {}", path, error);
+                        },
+                        Err(error) => warn!("could not serialize flock scene: {}", error)
+                    }
+                },
+                FlockSceneCommand::Load(path) => {
+                    let asset_server = resources.get::<AssetServer>().unwrap();
+                    let mut scene_spawner = resources.get_mut::<SceneSpawner>().unwrap();
+                    scene_spawner.spawn_dynamic(asset_server.load(path.as_str()));
+                }
+            }
+        }
+    }
+}
+
+impl Plugin for FlockScenePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_event::<FlockSceneCommand>()
+            .init_resource::<FlockSceneState>()
+            .add_system(Self::scene_commands.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ reflect::ReflectRef, scene::serde::SceneDeserializer };
+    use serde::de::DeserializeSeed;
+
+    use crate::headless::{ FlockDescription, HeadlessSimulation };
+    use super::*;
+
+    #[test]
+    fn flock_scenes_round_trip() {
+        let mut simulation = HeadlessSimulation::new(800.0, 600.0);
+        let description = FlockDescription::ring(Vec2::zero(), 40.0, 3).with_velocity(Vec2::new(12.0, -3.0));
+        let (flock, members) = simulation.spawn_flock(&description);
+        let other = simulation.app.world.spawn((Transform::default(), GlobalTransform::default()));
+
+        let type_registry = simulation.app.resources.get::<TypeRegistryArc>().unwrap();
+        let ron = FlockScenePlugin::flock_scene(&simulation.app.world, &type_registry).serialize_ron(&type_registry).unwrap();
+
+        let registry = type_registry.read();
+        let mut deserializer = ron::de::Deserializer::from_str(&ron).unwrap();
+        let scene = SceneDeserializer { type_registry: &*registry }.deserialize(&mut deserializer).unwrap();
+
+        let component = |entity: Entity, name: &str| scene.entities.iter()
+            .find(|x| x.entity == entity.id())
+            .and_then(|x| x.components.iter().find(|x| x.type_name() == name));
+
+        assert!(scene.entities.iter().all(|x| x.entity != other.id()));
+        assert_eq!(scene.entities.len(), members.len() + 1);
+        assert_eq!(component(flock, type_name::<Flock>()).and_then(|x| description.flock.reflect_partial_eq(&**x)), Some(true));
+
+        for member in members {
+            assert_eq!(component(member, type_name::<FlockMemberParams>()).and_then(|x| description.params.reflect_partial_eq(&**x)), Some(true));
+            assert_eq!(component(member, type_name::<Velocity>()).and_then(|x| Velocity(description.velocity).reflect_partial_eq(&**x)), Some(true));
+
+            // `Parent` only compares against a concrete `Parent`, so read its entity field instead
+            let parent = component(member, type_name::<Parent>()).and_then(|x| match x.reflect_ref() {
+                ReflectRef::TupleStruct(parent) => parent.field(0).and_then(|x| x.downcast_ref::<Entity>()).copied(),
+                _ => None
+            });
+            assert_eq!(parent, Some(flock));
+        }
+    }
+}
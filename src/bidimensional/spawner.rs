@@ -0,0 +1,229 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use super::{ FlockMember, FlockMemberParams, SimRng };
+
+/// Bounds may be given in either order, `Uniform(20.0, 10.0)` samples the same as `Uniform(10.0, 20.0)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamDistribution {
+    Fixed(f32),
+    Uniform(f32, f32),
+    Normal { mean: f32, std_dev: f32 }
+}
+
+impl ParamDistribution {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> f32 {
+        match *self {
+            ParamDistribution::Fixed(value) => value,
+            ParamDistribution::Uniform(low, high) => rng.gen_range(low.min(high)..=low.max(high)),
+            ParamDistribution::Normal { mean, std_dev } => {
+                // Box-Muller transform
+                let u1: f32 = rng.gen_range(f32::EPSILON..=1.0);
+                let u2: f32 = rng.gen();
+                mean + std_dev * (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+            }
+        }
+    }
+}
+
+/// Like `ParamDistribution`, inverted ring radii and negative extents are taken as their normalized counterparts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpawnShape {
+    Disc { radius: f32 },
+    Ring { inner_radius: f32, outer_radius: f32 },
+    Rect { half_extents: Vec2 },
+    Line { start: Vec2, end: Vec2 },
+    PoissonDisc { radius: f32, min_distance: f32 }
+}
+
+impl SpawnShape {
+    const POISSON_ATTEMPTS: usize = 30;
+
+    fn sample_ring<R: Rng>(inner_radius: f32, outer_radius: f32, rng: &mut R) -> Vec2 {
+        let (inner, outer) = (inner_radius.abs().min(outer_radius.abs()), inner_radius.abs().max(outer_radius.abs()));
+        let (inner, outer) = (inner * inner, outer * outer);
+        let radius = (inner + rng.gen::<f32>() * (outer - inner)).sqrt();
+        let angle = rng.gen_range(0.0..(2.0 * PI));
+
+        Vec2::new(angle.cos(), angle.sin()) * radius
+    }
+
+    /// Samples `count` points relative to the shape's center.
+    pub fn sample_points<R: Rng>(&self, count: usize, rng: &mut R) -> Vec<Vec2> {
+        let mut points: Vec<Vec2> = Vec::with_capacity(count);
+
+        for _ in 0..count {
+            let point = match *self {
+                SpawnShape::Disc { radius } => Self::sample_ring(0.0, radius, rng),
+                SpawnShape::Ring { inner_radius, outer_radius } => Self::sample_ring(inner_radius, outer_radius, rng),
+                SpawnShape::Rect { half_extents } => {
+                    let half_extents = half_extents.abs();
+                    Vec2::new(
+                        rng.gen_range(-half_extents.x..=half_extents.x),
+                        rng.gen_range(-half_extents.y..=half_extents.y)
+                    )
+                },
+                SpawnShape::Line { start, end } => start + (end - start) * rng.gen::<f32>(),
+                SpawnShape::PoissonDisc { radius, min_distance } => {
+                    // Dart throwing, falling back to the candidate furthest from its neighbors
+                    // when the disc is too crowded to honor `min_distance`.
+                    let mut best = (Vec2::zero(), -1.0);
+
+                    for _ in 0..Self::POISSON_ATTEMPTS {
+                        let candidate = Self::sample_ring(0.0, radius, rng);
+                        let nearest = points.iter()
+                            .map(|x| (*x - candidate).length())
+                            .fold(f32::INFINITY, f32::min);
+
+                        if nearest > best.1 {
+                            best = (candidate, nearest);
+                        }
+
+                        if nearest >= min_distance {
+                            break;
+                        }
+                    }
+
+                    best.0
+                }
+            };
+
+            points.push(point);
+        }
+
+        points
+    }
+}
+
+/// Describes the members of a flock. Add it alongside a `Flock` and the members are spawned as children
+/// of that entity on the next update, after which the spawner component is removed.
+///
+/// When `reference_size` is set, speed and acceleration are scaled by `reference_size / size` and the safe
+/// radius by `size / reference_size`, so smaller members are quicker and keep less distance.
+///
+/// Without a `seed`, members are drawn from the `SimRng` stream of the flock entity.
+#[derive(Debug, Clone)]
+pub struct FlockSpawner {
+    pub count: usize,
+    pub center: Vec2,
+    pub shape: SpawnShape,
+    pub size: ParamDistribution,
+    pub reference_size: Option<f32>,
+    pub max_speed: ParamDistribution,
+    pub max_accel: ParamDistribution,
+    pub safe_radius: ParamDistribution,
+    pub initial_velocity: ParamDistribution,
+    pub seed: Option<u64>,
+    /// Spawns members as sprites of their size, without it they only get transforms.
+    #[cfg(feature = "render")]
+    pub material: Option<Handle<ColorMaterial>>
+}
+
+impl Default for FlockSpawner {
+    fn default() -> Self {
+        FlockSpawner {
+            count: 100,
+            center: Vec2::zero(),
+            shape: SpawnShape::Disc { radius: 100.0 },
+            size: ParamDistribution::Fixed(16.0),
+            reference_size: None,
+            max_speed: ParamDistribution::Fixed(200.0),
+            max_accel: ParamDistribution::Fixed(30.0),
+            safe_radius: ParamDistribution::Fixed(50.0),
+            initial_velocity: ParamDistribution::Fixed(0.0),
+            seed: None,
+            #[cfg(feature = "render")]
+            material: None
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FlockSpawnerPlugin;
+
+impl FlockSpawnerPlugin {
+    fn spawn_members(commands: &mut Commands, sim_rng: Res<SimRng>, query: Query<(Entity, &FlockSpawner)>) {
+        for (entity, spawner) in query.iter() {
+            let mut rng = match spawner.seed {
+                Some(seed) => SimRng::new(seed).stream("spawner"),
+                None => sim_rng.entity_stream("spawner", entity)
+            };
+
+            let points = spawner.shape.sample_points(spawner.count, &mut rng);
+            let mut members = Vec::with_capacity(points.len());
+
+            for (i, point) in points.into_iter().enumerate() {
+                let size = spawner.size.sample(&mut rng);
+                let scale = spawner.reference_size.map(|x| x / size).unwrap_or(1.0);
+                let translation = (spawner.center + point).extend((i + 1) as f32);
+
+                let member = FlockMember {
+                    velocity: Vec2::new(spawner.initial_velocity.sample(&mut rng), spawner.initial_velocity.sample(&mut rng)).into(),
+                    params: FlockMemberParams {
+                        max_speed: spawner.max_speed.sample(&mut rng) * scale,
+                        max_accel: spawner.max_accel.sample(&mut rng) * scale,
+                        safe_radius: spawner.safe_radius.sample(&mut rng) / scale
+                    },
+                    ..Default::default()
+                };
+
+                #[cfg(feature = "render")]
+                match &spawner.material {
+                    Some(material) => commands.spawn(SpriteBundle {
+                        material: material.clone(),
+                        visible: Visible {
+                            is_transparent: true,
+                            ..Default::default()
+                        },
+                        sprite: Sprite::new(Vec2::new(size, size)),
+                        global_transform: GlobalTransform::from_translation(translation),
+                        ..Default::default()
+                    }),
+                    None => commands.spawn((Transform::default(), GlobalTransform::from_translation(translation)))
+                };
+
+                #[cfg(not(feature = "render"))]
+                commands.spawn((Transform::default(), GlobalTransform::from_translation(translation)));
+
+                members.push(commands.with_bundle(member).current_entity().unwrap());
+            }
+
+            commands
+                .push_children(entity, &members)
+                .remove_one::<FlockSpawner>(entity);
+        }
+    }
+}
+
+impl Plugin for FlockSpawnerPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .init_resource::<SimRng>()
+            .add_system_to_stage(stage::PRE_UPDATE, Self::spawn_members.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn inverted_bounds_are_normalized() {
+        let mut rng = SimRng::new(0).stream("inverted_bounds");
+
+        for _ in 0..100 {
+            let value = ParamDistribution::Uniform(20.0, 10.0).sample(&mut rng);
+            assert!(value >= 10.0 && value <= 20.0);
+        }
+
+        for point in SpawnShape::Rect { half_extents: Vec2::new(-10.0, -5.0) }.sample_points(100, &mut rng) {
+            assert!(point.x.abs() <= 10.0 && point.y.abs() <= 5.0);
+        }
+
+        for point in SpawnShape::Ring { inner_radius: 50.0, outer_radius: 20.0 }.sample_points(100, &mut rng) {
+            assert!(point.length() >= 20.0 - 1e-3 && point.length() <= 50.0 + 1e-3);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use bevy::prelude::*;
+
+use crate::util::Bounds;
+
+/// Extent of the simulated world, used for wrapping and wrap-aware distances. It follows the primary window
+/// unless `fixed`, which lets the simulation run without any window.
+#[derive(Debug, Clone, Copy)]
+pub struct WorldBounds {
+    pub bounds: Bounds<Vec2>,
+    pub fixed: bool
+}
+
+impl Default for WorldBounds {
+    fn default() -> Self {
+        WorldBounds {
+            bounds: Bounds::new(Vec2::new(-512.0, -400.0), Vec2::new(512.0, 400.0)),
+            fixed: false
+        }
+    }
+}
+
+impl WorldBounds {
+    pub fn fixed(bounds: Bounds<Vec2>) -> Self {
+        WorldBounds {
+            bounds,
+            fixed: true
+        }
+    }
+
+    /// Fixed bounds of `width` by `height` centered on the origin, like those of a window.
+    pub fn fixed_size(width: f32, height: f32) -> Self {
+        Self::fixed(Bounds::new(Vec2::new(-width / 2.0, -height / 2.0), Vec2::new(width / 2.0, height / 2.0)))
+    }
+}
+
+/// Runs on the main thread as `Windows` may not exist at all in headless apps.
+pub(crate) fn world_bounds(_world: &mut World, resources: &mut Resources) {
+    let windows = match resources.get::<Windows>() {
+        Some(windows) => windows,
+        None => return
+    };
+
+    let mut world_bounds = resources.get_mut::<WorldBounds>().unwrap();
+    if !world_bounds.fixed {
+        if let Some(window) = windows.get_primary() {
+            world_bounds.bounds = window.into();
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+use bevy::diagnostic::{ Diagnostic, DiagnosticId, Diagnostics, EntityCountDiagnosticsPlugin, FrameTimeDiagnosticsPlugin };
+
+use crate::{ bidimensional::{ FlockMemberMarker, FlockingProfile }, ui_camera::UiCamera };
+
+/// A line of the overlay. `Diagnostic` shows the average of any other registered `Diagnostics` id under its name.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OnScreenFpsLine {
+    Fps,
+    /// Min, average and max over the sparkline history.
+    FrameTime,
+    Entities,
+    FlockMembers,
+    FlockingTime,
+    NeighborPairs,
+    Diagnostic(DiagnosticId)
+}
+
+#[derive(Clone, Debug)]
+pub struct OnScreenFpsConfig {
+    pub font: &'static str,
+    pub text_style: TextStyle,
+    pub style: Style,
+    pub lines: Vec<OnScreenFpsLine>,
+    pub toggle_key: KeyCode,
+    /// Frames kept for the frame time sparkline and its min/avg/max.
+    pub history: usize,
+    pub sparkline_height: f32
+}
+
+impl Default for OnScreenFpsConfig {
+    fn default() -> Self {
+        OnScreenFpsConfig {
+            font: "fonts/Inconsolata.ttf",
+            text_style: Default::default(),
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(1.0),
+                    left: Val::Px(1.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: Rect::all(Val::Px(4.0)),
+                ..Default::default()
+            },
+            lines: vec![
+                OnScreenFpsLine::Fps,
+                OnScreenFpsLine::FrameTime,
+                OnScreenFpsLine::Entities,
+                OnScreenFpsLine::FlockMembers,
+                OnScreenFpsLine::FlockingTime,
+                OnScreenFpsLine::NeighborPairs
+            ],
+            toggle_key: KeyCode::F2,
+            history: 120,
+            sparkline_height: 40.0
+        }
+    }
+}
+
+impl OnScreenFpsConfig {
+    /// Adds a line for a `Diagnostics` id registered elsewhere.
+    pub fn with_diagnostic(mut self, id: DiagnosticId) -> Self {
+        self.lines.push(OnScreenFpsLine::Diagnostic(id));
+        self
+    }
+}
+
+/// Frame times in seconds, newest last. `Diagnostic` keeps its own history but does not expose it.
+/// `recorded` counts every frame ever pushed, which places each frame on the sparkline.
+#[derive(Clone, Debug, Default)]
+struct OnScreenFpsHistory {
+    frame_times: VecDeque<f64>,
+    recorded: usize
+}
+
+#[derive(Clone, Debug)]
+struct OnScreenFpsMarker;
+#[derive(Clone, Debug)]
+struct OnScreenFpsText(OnScreenFpsLine);
+#[derive(Clone, Debug)]
+struct OnScreenFpsBar(usize);
+#[derive(Clone, Debug)]
+pub struct OnScreenFpsPlugin(OnScreenFpsConfig);
+
+fn fps_diagnostics_setup(mut diagnostics: ResMut<Diagnostics>) {
+    diagnostics.add(Diagnostic::new(OnScreenFpsPlugin::FLOCK_MEMBER_COUNT, "flock_member_count", 20));
+    diagnostics.add(Diagnostic::new(OnScreenFpsPlugin::FLOCKING_TIME, "flocking_time", 20));
+    diagnostics.add(Diagnostic::new(OnScreenFpsPlugin::NEIGHBOR_PAIRS, "neighbor_pairs", 20));
+}
+
+fn fps_setup(commands: &mut Commands, config: Res<OnScreenFpsConfig>, asset_server: Res<AssetServer>, mut materials: ResMut<Assets<ColorMaterial>>, mut ui_camera: ResMut<UiCamera>) {
+    let font: Handle<Font> = asset_server.load(config.font);
+    let panel = materials.add(Color::rgba(0.0, 0.0, 0.0, 0.6).into());
+    let track = materials.add(Color::rgba(0.0, 0.0, 0.0, 0.0).into());
+    let bar = materials.add(Color::rgb(0.4, 0.6, 0.9).into());
+
+    ui_camera.spawn(commands);
+    commands
+        .spawn(NodeBundle {
+            style: config.style.clone(),
+            material: panel,
+            ..Default::default()
+        })
+        .with(OnScreenFpsMarker)
+        .with_children(|parent| {
+            for line in config.lines.iter() {
+                parent
+                    .spawn(TextBundle {
+                        text: Text {
+                            value: String::new(),
+                            font: font.clone(),
+                            style: config.text_style.clone(),
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    })
+                    .with(OnScreenFpsText(*line));
+            }
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Auto, Val::Px(config.sparkline_height)),
+                        flex_direction: FlexDirection::Row,
+                        align_items: AlignItems::FlexStart,
+                        margin: Rect::all(Val::Px(2.0)),
+                        ..Default::default()
+                    },
+                    material: track,
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    for i in 0..config.history {
+                        parent
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(2.0), Val::Percent(0.0)),
+                                    ..Default::default()
+                                },
+                                material: bar.clone(),
+                                ..Default::default()
+                            })
+                            .with(OnScreenFpsBar(i));
+                    }
+                });
+        });
+}
+
+fn fps_flocking_diagnostics(mut diagnostics: ResMut<Diagnostics>, profile: Res<FlockingProfile>, query: Query<&FlockMemberMarker>) {
+    diagnostics.add_measurement(OnScreenFpsPlugin::FLOCK_MEMBER_COUNT, query.iter().count() as f64);
+    diagnostics.add_measurement(OnScreenFpsPlugin::FLOCKING_TIME, profile.seconds);
+    diagnostics.add_measurement(OnScreenFpsPlugin::NEIGHBOR_PAIRS, profile.neighbor_pairs as f64);
+}
+
+fn fps_history(config: Res<OnScreenFpsConfig>, diagnostics: Res<Diagnostics>, mut history: ResMut<OnScreenFpsHistory>) {
+    if let Some(Some(frame_time)) = diagnostics.get(FrameTimeDiagnosticsPlugin::FRAME_TIME).map(|x| x.value()) {
+        history.frame_times.push_back(frame_time);
+        history.recorded += 1;
+        while history.frame_times.len() > config.history {
+            history.frame_times.pop_front();
+        }
+    }
+}
+
+fn fps_text(line: OnScreenFpsLine, diagnostics: &Diagnostics, history: &OnScreenFpsHistory) -> String {
+    let value = |id: DiagnosticId| diagnostics.get(id).and_then(|x| x.value());
+
+    let text = match line {
+        OnScreenFpsLine::Fps => value(FrameTimeDiagnosticsPlugin::FPS).map(|x| format!("fps {:.1}", x)),
+        OnScreenFpsLine::FrameTime if !history.frame_times.is_empty() => {
+            let min = history.frame_times.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = history.frame_times.iter().copied().fold(0.0, f64::max);
+            let average = history.frame_times.iter().sum::<f64>() / history.frame_times.len() as f64;
+            Some(format!("frame {:.2}/{:.2}/{:.2} ms", min * 1000.0, average * 1000.0, max * 1000.0))
+        },
+        OnScreenFpsLine::FrameTime => None,
+        OnScreenFpsLine::Entities => value(EntityCountDiagnosticsPlugin::ENTITY_COUNT).map(|x| format!("entities {}", x)),
+        OnScreenFpsLine::FlockMembers => value(OnScreenFpsPlugin::FLOCK_MEMBER_COUNT).map(|x| format!("members {}", x)),
+        OnScreenFpsLine::FlockingTime => value(OnScreenFpsPlugin::FLOCKING_TIME).map(|x| format!("flocking {:.2} ms", x * 1000.0)),
+        OnScreenFpsLine::NeighborPairs => value(OnScreenFpsPlugin::NEIGHBOR_PAIRS).map(|x| format!("neighbor pairs {}", x)),
+        OnScreenFpsLine::Diagnostic(id) => diagnostics.get(id)
+            .and_then(|x| x.average().or_else(|| x.value()).map(|value| format!("{} {:.3}", x.name, value)))
+    };
+
+    text.unwrap_or_else(|| "-".to_string())
+}
+
+fn fps_update(
+    config: Res<OnScreenFpsConfig>,
+    diagnostics: Res<Diagnostics>,
+    history: Res<OnScreenFpsHistory>,
+    mut text_query: Query<(&mut Text, &OnScreenFpsText)>,
+    mut bar_query: Query<(&mut Style, &OnScreenFpsBar)>
+) {
+    for (mut text, line) in text_query.iter_mut() {
+        let value = fps_text(line.0, &diagnostics, &history);
+        if text.value != value {
+            text.value = value;
+        }
+    }
+
+    // The sparkline sweeps left to right, frame `n` always lands on bar `n % history`, so only the newest
+    // bar changes unless the max does
+    let max = history.frame_times.iter().copied().fold(0.0, f64::max);
+    let oldest = history.recorded - history.frame_times.len();
+
+    for (mut style, bar) in bar_query.iter_mut() {
+        let index = (bar.0 + config.history - oldest % config.history) % config.history;
+        let fraction = match history.frame_times.get(index) {
+            Some(frame_time) if max > 0.0 => frame_time / max,
+            _ => 0.0
+        };
+
+        let height = Val::Percent(fraction as f32 * 100.0);
+        if style.size.height != height {
+            style.size.height = height;
+        }
+    }
+}
+
+fn fps_toggle(config: Res<OnScreenFpsConfig>, keys: Res<Input<KeyCode>>, mut query: Query<&mut Style, With<OnScreenFpsMarker>>) {
+    if keys.just_pressed(config.toggle_key) {
+        for mut style in query.iter_mut() {
+            style.display = match style.display {
+                Display::None => Display::Flex,
+                _ => Display::None
+            };
+        }
+    }
+}
+
+impl OnScreenFpsPlugin {
+    pub const FLOCK_MEMBER_COUNT: DiagnosticId = DiagnosticId::from_u128(155723389613678631795381332330770030534);
+    /// Seconds spent in `flocking`.
+    pub const FLOCKING_TIME: DiagnosticId = DiagnosticId::from_u128(247100166482404980780111072713146620994);
+    pub const NEIGHBOR_PAIRS: DiagnosticId = DiagnosticId::from_u128(174675556153350351493769047573294557081);
+
+    pub fn new(config: OnScreenFpsConfig) -> Self {
+        Self(config)
+    }
+}
+
+impl Plugin for OnScreenFpsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_resource(self.0.clone())
+            .init_resource::<OnScreenFpsHistory>()
+            .init_resource::<UiCamera>()
+            .init_resource::<FlockingProfile>()
+            .add_plugin(FrameTimeDiagnosticsPlugin)
+            .add_plugin(EntityCountDiagnosticsPlugin)
+            .add_startup_system(fps_diagnostics_setup.system())
+            .add_startup_system(fps_setup.system())
+            .add_system_to_stage(stage::POST_UPDATE, fps_flocking_diagnostics.system())
+            .add_system_to_stage(stage::POST_UPDATE, fps_history.system())
+            .add_system(fps_update.system())
+            .add_system(fps_toggle.system());
+    }
+}
@@ -0,0 +1,151 @@
+use bevy::{ prelude::*, app::App, transform::TransformPlugin };
+
+use crate::bidimensional::{ Flock, FlockMember, FlockMemberParams, FlockStats, FlockingPlugin, MovementPlugin, SimulationClock, Velocity, WorldBounds };
+
+/// Members of a single flock to spawn in a `HeadlessSimulation`, all sharing `velocity` and `params`.
+#[derive(Debug, Clone)]
+pub struct FlockDescription {
+    pub flock: Flock,
+    pub params: FlockMemberParams,
+    pub velocity: Vec2,
+    pub positions: Vec<Vec2>
+}
+
+impl FlockDescription {
+    pub fn at(positions: &[Vec2]) -> Self {
+        FlockDescription {
+            flock: Flock {
+                flock_radius: 50.0,
+                alignment_strength: 1.0,
+                cohesion_strength: 1.0,
+                separation_strength: 1.0
+            },
+            params: FlockMember::default().params,
+            velocity: Vec2::zero(),
+            positions: positions.to_vec()
+        }
+    }
+
+    /// `count` members evenly spaced on a circle.
+    pub fn ring(center: Vec2, radius: f32, count: usize) -> Self {
+        let positions: Vec<Vec2> = (0..count)
+            .map(|i| {
+                let angle = i as f32 / count as f32 * std::f32::consts::PI * 2.0;
+                center + Vec2::new(angle.cos(), angle.sin()) * radius
+            })
+            .collect();
+
+        Self::at(&positions)
+    }
+
+    /// `columns` by `rows` members, `spacing` apart and centered on `center`.
+    pub fn grid(center: Vec2, columns: usize, rows: usize, spacing: f32) -> Self {
+        let corner = center - Vec2::new(columns.saturating_sub(1) as f32, rows.saturating_sub(1) as f32) * spacing / 2.0;
+        let positions: Vec<Vec2> = (0..rows)
+            .flat_map(|y| (0..columns).map(move |x| corner + Vec2::new(x as f32, y as f32) * spacing))
+            .collect();
+
+        Self::at(&positions)
+    }
+
+    pub fn with_flock(mut self, flock: Flock) -> Self {
+        self.flock = flock;
+        self
+    }
+
+    pub fn with_params(mut self, params: FlockMemberParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    pub fn with_velocity(mut self, velocity: Vec2) -> Self {
+        self.velocity = velocity;
+        self
+    }
+}
+
+/// Runs `MovementPlugin` and `FlockingPlugin` without a window, in fixed bounds and with a fixed time step,
+/// so every run with the same flocks gives the same result. `TransformPlugin` keeps `Children` in step with
+/// reassigned `Parent`s and propagates `Transform`s, as it does in the windowed app.
+pub struct HeadlessSimulation {
+    pub app: App
+}
+
+impl HeadlessSimulation {
+    pub const DELTA_SECONDS: f32 = 1.0 / 60.0;
+
+    pub fn new(width: f32, height: f32) -> Self {
+        Self::with_plugins(width, height, |_| {})
+    }
+
+    /// Like `new`, with `configure` adding further plugins and resources before the app is built.
+    pub fn with_plugins(width: f32, height: f32, configure: impl FnOnce(&mut AppBuilder)) -> Self {
+        let mut builder = App::build();
+        builder
+            .add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
+            .add_resource(WorldBounds::fixed_size(width, height))
+            .add_resource(SimulationClock::fixed(Self::DELTA_SECONDS))
+            .add_plugin(MovementPlugin)
+            .add_plugin(FlockingPlugin::with_wrapping());
+
+        configure(&mut builder);
+
+        HeadlessSimulation {
+            app: builder.app
+        }
+    }
+
+    /// Spawns the flock and its members, returning the flock entity and its members in the order of `description.positions`.
+    pub fn spawn_flock(&mut self, description: &FlockDescription) -> (Entity, Vec<Entity>) {
+        let world = &mut self.app.world;
+        let flock = world.spawn((description.flock,));
+
+        let members: Vec<Entity> = description.positions.iter()
+            .map(|position| {
+                let member = world.spawn(FlockMember {
+                    velocity: description.velocity.into(),
+                    params: description.params,
+                    ..Default::default()
+                });
+
+                world.insert(member, (GlobalTransform::from_translation(position.extend(0.0)), Parent(flock))).unwrap();
+                member
+            })
+            .collect();
+
+        world.insert_one(flock, Children::with(&members)).unwrap();
+        (flock, members)
+    }
+
+    pub fn step(&mut self, ticks: usize) {
+        for _ in 0..ticks {
+            self.app.update();
+        }
+    }
+
+    pub fn position(&self, member: Entity) -> Vec2 {
+        self.app.world.get::<GlobalTransform>(member).unwrap().translation.truncate()
+    }
+
+    pub fn velocity(&self, member: Entity) -> Vec2 {
+        self.app.world.get::<Velocity>(member).unwrap().0
+    }
+
+    /// `None` until the flock has been through a tick.
+    pub fn stats(&self, flock: Entity) -> Option<FlockStats> {
+        self.app.world.get::<FlockStats>(flock).ok().map(|x| *x)
+    }
+
+    pub fn bounds(&self) -> WorldBounds {
+        *self.app.resources.get::<WorldBounds>().unwrap()
+    }
+
+    pub fn clock(&self) -> SimulationClock {
+        self.app.resources.get::<SimulationClock>().unwrap().clone()
+    }
+
+    pub fn set_clock(&mut self, clock: SimulationClock) {
+        self.app.resources.insert(clock);
+    }
+}
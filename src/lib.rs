@@ -0,0 +1,9 @@
+pub mod util;
+pub mod bidimensional;
+pub mod headless;
+pub mod plugins;
+#[cfg(any(feature = "fps", feature = "tools"))]
+mod ui_camera;
+
+#[cfg(feature = "fps")]
+pub mod fps;
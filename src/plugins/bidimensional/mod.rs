@@ -1,6 +0,0 @@
-
-mod movement;
-mod flock;
-
-pub use movement::*;
-pub use flock::*;
\ No newline at end of file
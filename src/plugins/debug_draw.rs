@@ -0,0 +1,317 @@
+use bevy::{
+    prelude::*,
+    render::texture::{ Extent3d, TextureDimension, TextureFormat }
+};
+
+use crate::bidimensional::{ Flock, FlockMemberMarker, FlockMemberParams, FlockStats, FlockSteering, Velocity };
+
+/// Runs between `UPDATE` and `POST_UPDATE`, after steering is known and before `Transform`s are propagated.
+pub const DEBUG_DRAW_STAGE: &'static str = "DEBUG_DRAW";
+
+/// `cone_half_angle` only affects the drawn perception cone; `flocking` itself takes the whole flock into account.
+#[derive(Clone, Debug)]
+pub struct DebugDrawConfig {
+    pub enabled: bool,
+    pub toggle_key: KeyCode,
+    pub vector_scale: f32,
+    pub line_thickness: f32,
+    pub cone_half_angle: f32,
+    pub z: f32,
+    pub velocity_color: Color,
+    pub alignment_color: Color,
+    pub cohesion_color: Color,
+    pub separation_color: Color,
+    pub radius_color: Color,
+    pub flock_color: Color
+}
+
+impl Default for DebugDrawConfig {
+    fn default() -> Self {
+        DebugDrawConfig {
+            enabled: false,
+            toggle_key: KeyCode::F3,
+            vector_scale: 0.25,
+            line_thickness: 1.0,
+            cone_half_angle: std::f32::consts::FRAC_PI_3,
+            z: 900.0,
+            velocity_color: Color::WHITE,
+            alignment_color: Color::GREEN,
+            cohesion_color: Color::rgb(1.0, 1.0, 0.0),
+            separation_color: Color::RED,
+            radius_color: Color::rgba(1.0, 1.0, 1.0, 0.25),
+            flock_color: Color::rgb(0.0, 1.0, 1.0)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DebugShape {
+    Line { start: Vec2, end: Vec2 },
+    Circle { center: Vec2, radius: f32 }
+}
+
+/// Shapes to draw this frame. Other systems may push into it before `DEBUG_DRAW_STAGE` while the overlay
+/// is enabled, it is cleared after every frame.
+#[derive(Debug, Default)]
+pub struct DebugShapes {
+    shapes: Vec<(DebugShape, Color)>
+}
+
+impl DebugShapes {
+    pub fn line(&mut self, start: Vec2, end: Vec2, color: Color) {
+        self.shapes.push((DebugShape::Line { start, end }, color));
+    }
+
+    pub fn circle(&mut self, center: Vec2, radius: f32, color: Color) {
+        self.shapes.push((DebugShape::Circle { center, radius }, color));
+    }
+}
+
+struct DebugDrawMarker;
+
+struct DebugDrawPool {
+    lines: Vec<Entity>,
+    circles: Vec<Entity>,
+    materials: Vec<(Color, bool, Handle<ColorMaterial>)>,
+    ring: Handle<Texture>
+}
+
+impl DebugDrawPool {
+    const RING_SIZE: u32 = 128;
+    const RING_THICKNESS: f32 = 2.0;
+
+    fn ring_texture() -> Texture {
+        let radius = Self::RING_SIZE as f32 / 2.0;
+        let mut data = Vec::with_capacity((Self::RING_SIZE * Self::RING_SIZE * 4) as usize);
+
+        for y in 0..Self::RING_SIZE {
+            for x in 0..Self::RING_SIZE {
+                let distance = (Vec2::new(x as f32 + 0.5, y as f32 + 0.5) - Vec2::splat(radius)).length();
+                let alpha = if distance <= radius && distance >= radius - Self::RING_THICKNESS { 255 } else { 0 };
+                data.extend_from_slice(&[255, 255, 255, alpha]);
+            }
+        }
+
+        Texture::new(Extent3d::new(Self::RING_SIZE, Self::RING_SIZE, 1), TextureDimension::D2, data, TextureFormat::Rgba8UnormSrgb)
+    }
+
+    fn material(&mut self, materials: &mut Assets<ColorMaterial>, color: Color, textured: bool) -> Handle<ColorMaterial> {
+        if let Some((_, _, handle)) = self.materials.iter().find(|(x, y, _)| *x == color && *y == textured) {
+            return handle.clone();
+        }
+
+        let handle = materials.add(ColorMaterial {
+            color,
+            texture: if textured { Some(self.ring.clone()) } else { None }
+        });
+
+        self.materials.push((color, textured, handle.clone()));
+        handle
+    }
+}
+
+impl FromResources for DebugDrawPool {
+    fn from_resources(resources: &Resources) -> Self {
+        let mut textures = resources.get_mut::<Assets<Texture>>().unwrap();
+
+        DebugDrawPool {
+            lines: Vec::new(),
+            circles: Vec::new(),
+            materials: Vec::new(),
+            ring: textures.add(Self::ring_texture())
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DebugDrawPlugin(DebugDrawConfig);
+
+impl DebugDrawPlugin {
+    pub fn new(config: DebugDrawConfig) -> Self {
+        Self(config)
+    }
+
+    fn rotate(vector: Vec2, angle: f32) -> Vec2 {
+        let (sin, cos) = angle.sin_cos();
+        Vec2::new(vector.x * cos - vector.y * sin, vector.x * sin + vector.y * cos)
+    }
+
+    fn debug_toggle(keys: Res<Input<KeyCode>>, mut config: ResMut<DebugDrawConfig>) {
+        if keys.just_pressed(config.toggle_key) {
+            config.enabled = !config.enabled;
+        }
+    }
+
+    fn attach_steering(commands: &mut Commands, config: Res<DebugDrawConfig>, query: Query<Entity, (With<FlockMemberMarker>, Without<FlockSteering>)>) {
+        if config.enabled {
+            for entity in query.iter() {
+                commands.insert_one(entity, FlockSteering::default());
+            }
+        }
+    }
+
+    fn debug_collect(
+        config: Res<DebugDrawConfig>,
+        mut shapes: ResMut<DebugShapes>,
+        flock_query: Query<(&Flock, Option<&FlockStats>)>,
+        member_query: Query<(&GlobalTransform, &Velocity, &FlockMemberParams, Option<&FlockSteering>, Option<&Parent>), With<FlockMemberMarker>>
+    ) {
+        if !config.enabled {
+            return;
+        }
+
+        for (flock, stats) in flock_query.iter() {
+            if let Some(stats) = stats {
+                let arm = Vec2::splat(5.0);
+                shapes.line(stats.centroid - arm, stats.centroid + arm, config.flock_color);
+                shapes.line(stats.centroid + Vec2::new(-arm.x, arm.y), stats.centroid + Vec2::new(arm.x, -arm.y), config.flock_color);
+                shapes.circle(stats.centroid, flock.flock_radius, config.flock_color);
+            }
+        }
+
+        for (transform, velocity, params, steering, parent) in member_query.iter() {
+            let position = transform.translation.truncate();
+
+            shapes.line(position, position + velocity.0 * config.vector_scale, config.velocity_color);
+            shapes.circle(position, params.safe_radius, config.radius_color);
+
+            if let Some(steering) = steering {
+                shapes.line(position, position + steering.alignment * config.vector_scale, config.alignment_color);
+                shapes.line(position, position + steering.cohesion * config.vector_scale, config.cohesion_color);
+                shapes.line(position, position + steering.separation * config.vector_scale, config.separation_color);
+            }
+
+            let flock_radius = parent
+                .and_then(|x| flock_query.get(x.0).ok())
+                .map(|(flock, _)| flock.flock_radius);
+
+            if let (Some(flock_radius), true) = (flock_radius, velocity.0.length_squared() > 0.0) {
+                let heading = velocity.0.normalize() * flock_radius;
+                shapes.line(position, position + Self::rotate(heading, config.cone_half_angle), config.radius_color);
+                shapes.line(position, position + Self::rotate(heading, -config.cone_half_angle), config.radius_color);
+            }
+        }
+    }
+
+    fn debug_render(
+        commands: &mut Commands,
+        config: Res<DebugDrawConfig>,
+        mut shapes: ResMut<DebugShapes>,
+        mut pool: ResMut<DebugDrawPool>,
+        mut materials: ResMut<Assets<ColorMaterial>>,
+        mut query: Query<(&mut Transform, &mut Sprite, &mut Handle<ColorMaterial>, &mut Visible), With<DebugDrawMarker>>
+    ) {
+        let (mut line_count, mut circle_count) = (0, 0);
+
+        if !config.enabled {
+            shapes.shapes.clear();
+        }
+
+        for (shape, color) in shapes.shapes.drain(..) {
+            let (transform, size, textured) = match shape {
+                DebugShape::Line { start, end } => {
+                    let difference = end - start;
+                    let transform = Transform {
+                        translation: ((start + end) / 2.0).extend(config.z),
+                        rotation: Quat::from_rotation_z(difference.y.atan2(difference.x)),
+                        scale: Vec3::one()
+                    };
+                    (transform, Vec2::new(difference.length(), config.line_thickness), false)
+                },
+                DebugShape::Circle { center, radius } => {
+                    (Transform::from_translation(center.extend(config.z)), Vec2::splat(radius * 2.0), true)
+                }
+            };
+
+            let material = pool.material(&mut materials, color, textured);
+            let index = if textured { &mut circle_count } else { &mut line_count };
+            let existing = (if textured { pool.circles.get(*index) } else { pool.lines.get(*index) }).copied();
+            *index += 1;
+
+            match existing {
+                Some(entity) => {
+                    // Pool sprites are root entities, `GlobalTransform` is propagated from `Transform`
+                    if let Ok((mut local, mut sprite, mut handle, mut visible)) = query.get_mut(entity) {
+                        *local = transform;
+                        sprite.size = size;
+                        *handle = material;
+                        visible.is_visible = true;
+                    }
+                },
+                None => {
+                    let entity = commands
+                        .spawn(SpriteBundle {
+                            material,
+                            sprite: Sprite::new(size),
+                            transform,
+                            visible: Visible {
+                                is_visible: true,
+                                is_transparent: true
+                            },
+                            ..Default::default()
+                        })
+                        .with(DebugDrawMarker)
+                        .current_entity()
+                        .unwrap();
+
+                    if textured {
+                        pool.circles.push(entity);
+                    } else {
+                        pool.lines.push(entity);
+                    }
+                }
+            }
+        }
+
+        let unused = pool.lines.iter().skip(line_count).chain(pool.circles.iter().skip(circle_count));
+        for entity in unused {
+            if let Ok((_, _, _, mut visible)) = query.get_mut(*entity) {
+                visible.is_visible = false;
+            }
+        }
+    }
+}
+
+impl Plugin for DebugDrawPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_resource(self.0.clone())
+            .init_resource::<DebugShapes>()
+            .init_resource::<DebugDrawPool>()
+            .add_system(Self::debug_toggle.system())
+            .add_system(Self::attach_steering.system())
+            .add_stage_after(stage::UPDATE, DEBUG_DRAW_STAGE, SystemStage::serial())
+            .add_system_to_stage(DEBUG_DRAW_STAGE, Self::debug_collect.system())
+            .add_system_to_stage(DEBUG_DRAW_STAGE, Self::debug_render.system());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::asset::AssetPlugin;
+
+    use crate::headless::HeadlessSimulation;
+    use super::*;
+
+    #[test]
+    fn pooled_shapes_are_placed_by_transform() {
+        let mut simulation = HeadlessSimulation::with_plugins(800.0, 600.0, |app| {
+            app
+                .add_plugin(AssetPlugin)
+                .add_asset::<Texture>()
+                .add_asset::<ColorMaterial>()
+                .init_resource::<Input<KeyCode>>()
+                .add_plugin(DebugDrawPlugin::new(DebugDrawConfig {
+                    enabled: true,
+                    ..Default::default()
+                }));
+        });
+
+        simulation.app.resources.get_mut::<DebugShapes>().unwrap().circle(Vec2::new(120.0, -40.0), 10.0, Color::WHITE);
+        simulation.step(1);
+
+        let circle = simulation.app.resources.get::<DebugDrawPool>().unwrap().circles[0];
+        let transform = simulation.app.world.get::<GlobalTransform>(circle).unwrap();
+        assert_eq!(transform.translation, Vec3::new(120.0, -40.0, DebugDrawConfig::default().z));
+    }
+}
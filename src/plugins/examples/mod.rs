@@ -1,4 +0,0 @@
-
-mod simple_flocking;
-
-pub use simple_flocking::*;
@@ -0,0 +1,217 @@
+use bevy::{
+    prelude::*,
+    input::touch::{ TouchInput, TouchPhase },
+    render::{ camera::Camera, render_graph::base::camera::CAMERA_2D }
+};
+
+use crate::bidimensional::{ Attractor, Falloff, Flock, FlockFilter, FlockMember, FlockMemberMarker, FlockMemberParams, FlockStats, ForceField, Repulsor, SimulationClock };
+
+#[derive(Clone, Debug)]
+pub struct PointerInteractionConfig {
+    pub attractor_strength: f32,
+    pub repeller_strength: f32,
+    pub radius: f32,
+    pub lifetime: f32,
+    pub attract_button: MouseButton,
+    pub repel_button: MouseButton,
+    pub spawn_modifier: KeyCode
+}
+
+impl Default for PointerInteractionConfig {
+    fn default() -> Self {
+        PointerInteractionConfig {
+            attractor_strength: 1.0,
+            repeller_strength: 2.0,
+            radius: 250.0,
+            lifetime: 3.0,
+            attract_button: MouseButton::Left,
+            repel_button: MouseButton::Right,
+            spawn_modifier: KeyCode::LShift
+        }
+    }
+}
+
+/// Marks an `Attractor` or `Repulsor` created by a click or touch, which is despawned once `remaining` runs out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PointerForce {
+    pub remaining: f32
+}
+
+#[derive(Debug, Default)]
+struct PointerState {
+    cursor_reader: EventReader<CursorMoved>,
+    touch_reader: EventReader<TouchInput>,
+    cursor: Option<Vec2>
+}
+
+/// Requires `ForceFieldPlugin` for the attractors and repellers to have an effect. Clicks and touches over
+/// a visible UI panel are left to the UI.
+#[derive(Clone, Debug, Default)]
+pub struct PointerInteractionPlugin(PointerInteractionConfig);
+
+impl PointerInteractionPlugin {
+    pub fn new(config: PointerInteractionConfig) -> Self {
+        Self(config)
+    }
+
+    /// Converts a window position (origin at the bottom left) to world space through the 2d camera.
+    fn to_world(position: Vec2, window: &Window, camera_query: &Query<(&Camera, &GlobalTransform)>) -> Option<Vec2> {
+        let (_, transform) = camera_query.iter().find(|(camera, _)| camera.name.as_deref() == Some(CAMERA_2D))?;
+        let centered = position - Vec2::new(window.width(), window.height()) / 2.0;
+
+        Some((transform.compute_matrix() * centered.extend(0.0).extend(1.0)).truncate().truncate())
+    }
+
+    /// Whether a window position (origin at the bottom left, like UI node positions) is over a shown UI root.
+    /// Roots are the panels, which contain all of their nodes.
+    fn over_ui(position: Vec2, ui_query: &Query<(&Node, &GlobalTransform, &Style), Without<Parent>>) -> bool {
+        ui_query.iter().any(|(node, transform, style)| {
+            let offset = position - transform.translation.truncate();
+            style.display != Display::None && offset.x.abs() <= node.size.x / 2.0 && offset.y.abs() <= node.size.y / 2.0
+        })
+    }
+
+    fn spawn_member(commands: &mut Commands, position: Vec2, flock_query: &Query<(Entity, &FlockStats, &Children), With<Flock>>, member_query: &Query<(&FlockMemberParams, Option<&Handle<ColorMaterial>>, Option<&Sprite>), With<FlockMemberMarker>>) {
+        let nearest = flock_query.iter()
+            .filter(|(_, stats, _)| stats.member_count > 0)
+            .min_by(|(_, a, _), (_, b, _)| {
+                (a.centroid - position).length_squared()
+                    .partial_cmp(&(b.centroid - position).length_squared())
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            });
+
+        let (flock, children) = match nearest {
+            Some((flock, _, children)) => (flock, children),
+            None => return
+        };
+
+        let template = match children.iter().find_map(|x| member_query.get(*x).ok()) {
+            Some(template) => template,
+            None => return
+        };
+
+        let (params, material, sprite) = template;
+        let transform = GlobalTransform::from_translation(position.extend(children.len() as f32 + 1.0));
+
+        match (material, sprite) {
+            (Some(material), Some(sprite)) => commands.spawn(SpriteBundle {
+                material: material.clone(),
+                visible: Visible {
+                    is_transparent: true,
+                    ..Default::default()
+                },
+                sprite: Sprite::new(sprite.size),
+                global_transform: transform,
+                ..Default::default()
+            }),
+            _ => commands.spawn((Transform::default(), transform))
+        };
+
+        let member = commands
+            .with_bundle(FlockMember {
+                params: *params,
+                ..Default::default()
+            })
+            .current_entity()
+            .unwrap();
+
+        commands.push_children(flock, &[member]);
+    }
+
+    fn pointer_input(
+        commands: &mut Commands,
+        config: Res<PointerInteractionConfig>,
+        mut state: Local<PointerState>,
+        windows: Res<Windows>,
+        cursor_events: Res<Events<CursorMoved>>,
+        touch_events: Res<Events<TouchInput>>,
+        buttons: Res<Input<MouseButton>>,
+        keys: Res<Input<KeyCode>>,
+        camera_query: Query<(&Camera, &GlobalTransform)>,
+        ui_query: Query<(&Node, &GlobalTransform, &Style), Without<Parent>>,
+        flock_query: Query<(Entity, &FlockStats, &Children), With<Flock>>,
+        member_query: Query<(&FlockMemberParams, Option<&Handle<ColorMaterial>>, Option<&Sprite>), With<FlockMemberMarker>>
+    ) {
+        let window = match windows.get_primary() {
+            Some(window) => window,
+            None => return
+        };
+
+        if let Some(event) = state.cursor_reader.latest(&cursor_events) {
+            state.cursor = Some(event.position);
+        }
+
+        let mut presses = Vec::new();
+
+        if let Some(cursor) = state.cursor {
+            if buttons.just_pressed(config.attract_button) {
+                presses.push((cursor, config.attractor_strength, keys.pressed(config.spawn_modifier)));
+            }
+
+            if buttons.just_pressed(config.repel_button) {
+                presses.push((cursor, -config.repeller_strength, false));
+            }
+        }
+
+        for event in state.touch_reader.iter(&touch_events) {
+            if event.phase == TouchPhase::Started {
+                // Touch positions have their origin at the top left, unlike the cursor
+                let position = Vec2::new(event.position.x, window.height() - event.position.y);
+                presses.push((position, config.attractor_strength, false));
+            }
+        }
+
+        for (position, strength, spawn) in presses {
+            if Self::over_ui(position, &ui_query) {
+                continue;
+            }
+
+            let position = match Self::to_world(position, window, &camera_query) {
+                Some(position) => position,
+                None => continue
+            };
+
+            if spawn {
+                Self::spawn_member(commands, position, &flock_query, &member_query);
+                continue;
+            }
+
+            let field = ForceField {
+                radius: config.radius,
+                strength: strength.abs(),
+                falloff: Falloff::Linear,
+                flocks: FlockFilter::All
+            };
+
+            commands.spawn((
+                GlobalTransform::from_translation(position.extend(0.0)),
+                PointerForce { remaining: config.lifetime }
+            ));
+
+            if strength < 0.0 {
+                commands.with(Repulsor(field));
+            } else {
+                commands.with(Attractor(field));
+            }
+        }
+    }
+
+    fn pointer_forces(commands: &mut Commands, clock: Res<SimulationClock>, mut query: Query<(Entity, &mut PointerForce)>) {
+        for (entity, mut force) in query.iter_mut() {
+            force.remaining -= clock.delta_seconds();
+            if force.remaining <= 0.0 {
+                commands.despawn(entity);
+            }
+        }
+    }
+}
+
+impl Plugin for PointerInteractionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_resource(self.0.clone())
+            .init_resource::<SimulationClock>()
+            .add_system(Self::pointer_input.system())
+            .add_system(Self::pointer_forces.system());
+    }
+}
@@ -0,0 +1,283 @@
+use std::{ collections::HashMap, fs::File, io::{ BufWriter, Write }, path::PathBuf };
+
+use bevy::{ prelude::*, app::AppExit };
+
+use crate::{ util::*, bidimensional::{ Flock, FlockMemberMarker, FlockStats, MemberCollision, SimulationClock, Velocity, WorldBounds } };
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlockMetricsFormat {
+    Csv,
+    JsonLines
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlockMetric {
+    Polarization,
+    Spread,
+    NearestNeighbor,
+    /// `MemberCollision` events involving the flock since the last row, always 0 without `MemberCollisionPlugin`.
+    Collisions,
+    SpeedHistogram
+}
+
+#[derive(Clone, Debug)]
+pub struct FlockMetricsConfig {
+    pub path: PathBuf,
+    pub format: FlockMetricsFormat,
+    pub interval: u32,
+    pub metrics: Vec<FlockMetric>,
+    pub speed_bin_width: f32,
+    pub speed_bins: usize,
+    /// Rows written between flushes, the file is also flushed when the app exits.
+    pub flush_rows: usize
+}
+
+impl Default for FlockMetricsConfig {
+    fn default() -> Self {
+        FlockMetricsConfig {
+            path: "metrics.csv".into(),
+            format: FlockMetricsFormat::Csv,
+            interval: 1,
+            metrics: vec![
+                FlockMetric::Polarization,
+                FlockMetric::Spread,
+                FlockMetric::NearestNeighbor,
+                FlockMetric::Collisions,
+                FlockMetric::SpeedHistogram
+            ],
+            speed_bin_width: 25.0,
+            speed_bins: 10,
+            flush_rows: 256
+        }
+    }
+}
+
+struct FlockMetricsWriter {
+    writer: Option<BufWriter<File>>,
+    header_written: bool,
+    unflushed_rows: usize
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+struct FlockSample {
+    polarization: f32,
+    spread: f32,
+    nearest_neighbor: [f32; 4],
+    collisions: usize,
+    speed_histogram: Vec<usize>
+}
+
+#[derive(Clone, Debug)]
+pub struct FlockMetricsPlugin(FlockMetricsConfig);
+
+impl FlockMetricsPlugin {
+    pub fn new(config: FlockMetricsConfig) -> Self {
+        Self(config)
+    }
+
+    /// Returns the minimum, mean, median and maximum nearest neighbor distance, measured across the wrapping edges of `bounds`.
+    fn nearest_neighbors(positions: &[Vec2], bounds: Bounds<Vec2>) -> [f32; 4] {
+        let mut nearest = vec![f32::INFINITY; positions.len()];
+
+        for i in 0..positions.len() {
+            for j in (i + 1)..positions.len() {
+                let distance = positions[i].bound_to(positions[j], bounds).length();
+                nearest[i] = nearest[i].min(distance);
+                nearest[j] = nearest[j].min(distance);
+            }
+        }
+
+        if positions.len() < 2 {
+            return [0.0; 4];
+        }
+
+        nearest.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let mean = nearest.iter().sum::<f32>() / nearest.len() as f32;
+        let median = nearest[nearest.len() / 2];
+
+        [nearest[0], mean, median, nearest[nearest.len() - 1]]
+    }
+
+    fn speed_histogram(velocities: &[Vec2], bin_width: f32, bins: usize) -> Vec<usize> {
+        let mut histogram = vec![0; bins];
+
+        if bins > 0 && bin_width > 0.0 {
+            for velocity in velocities.iter() {
+                let bin = (velocity.length() / bin_width) as usize;
+                histogram[bin.min(bins - 1)] += 1;
+            }
+        }
+
+        histogram
+    }
+
+    fn header(config: &FlockMetricsConfig) -> String {
+        let mut columns = vec!["tick".to_string(), "time".to_string(), "flock".to_string(), "member_count".to_string()];
+
+        for metric in config.metrics.iter() {
+            match metric {
+                FlockMetric::Polarization => columns.push("polarization".to_string()),
+                FlockMetric::Spread => columns.push("spread".to_string()),
+                FlockMetric::NearestNeighbor => columns.extend(["nn_min", "nn_mean", "nn_median", "nn_max"].iter().map(|x| x.to_string())),
+                FlockMetric::Collisions => columns.push("collisions".to_string()),
+                FlockMetric::SpeedHistogram => columns.extend((0..config.speed_bins).map(|x| format!("speed_bin_{}", x)))
+            }
+        }
+
+        columns.join(",")
+    }
+
+    fn csv_row(config: &FlockMetricsConfig, tick: u64, time: f64, flock: Entity, stats: &FlockStats, sample: &FlockSample) -> String {
+        let mut columns = vec![tick.to_string(), time.to_string(), flock.id().to_string(), stats.member_count.to_string()];
+
+        for metric in config.metrics.iter() {
+            match metric {
+                FlockMetric::Polarization => columns.push(sample.polarization.to_string()),
+                FlockMetric::Spread => columns.push(sample.spread.to_string()),
+                FlockMetric::NearestNeighbor => columns.extend(sample.nearest_neighbor.iter().map(|x| x.to_string())),
+                FlockMetric::Collisions => columns.push(sample.collisions.to_string()),
+                FlockMetric::SpeedHistogram => columns.extend(sample.speed_histogram.iter().map(|x| x.to_string()))
+            }
+        }
+
+        columns.join(",")
+    }
+
+    fn json_row(config: &FlockMetricsConfig, tick: u64, time: f64, flock: Entity, stats: &FlockStats, sample: &FlockSample) -> String {
+        let mut fields = vec![
+            format!("\"tick\":{}", tick),
+            format!("\"time\":{}", time),
+            format!("\"flock\":{}", flock.id()),
+            format!("\"member_count\":{}", stats.member_count)
+        ];
+
+        for metric in config.metrics.iter() {
+            match metric {
+                FlockMetric::Polarization => fields.push(format!("\"polarization\":{}", sample.polarization)),
+                FlockMetric::Spread => fields.push(format!("\"spread\":{}", sample.spread)),
+                FlockMetric::NearestNeighbor => {
+                    let [min, mean, median, max] = sample.nearest_neighbor;
+                    fields.push(format!("\"nearest_neighbor\":{{\"min\":{},\"mean\":{},\"median\":{},\"max\":{}}}", min, mean, median, max));
+                },
+                FlockMetric::Collisions => fields.push(format!("\"collisions\":{}", sample.collisions)),
+                FlockMetric::SpeedHistogram => {
+                    let bins: Vec<String> = sample.speed_histogram.iter().map(|x| x.to_string()).collect();
+                    fields.push(format!("\"speed_histogram\":[{}]", bins.join(",")));
+                }
+            }
+        }
+
+        format!("{{{}}}", fields.join(","))
+    }
+
+    fn record(
+        config: Res<FlockMetricsConfig>,
+        clock: Res<SimulationClock>,
+        world_bounds: Res<WorldBounds>,
+        mut output: ResMut<FlockMetricsWriter>,
+        mut collisions: Local<HashMap<Entity, usize>>,
+        mut collision_reader: Local<EventReader<MemberCollision>>,
+        collision_events: Res<Events<MemberCollision>>,
+        mut exit_reader: Local<EventReader<AppExit>>,
+        exit_events: Res<Events<AppExit>>,
+        query: Query<(Entity, &Children, &FlockStats), With<Flock>>,
+        child_query: Query<(&GlobalTransform, &Velocity), With<FlockMemberMarker>>,
+        parent_query: Query<&Parent, With<FlockMemberMarker>>
+    ) {
+        // Collisions are counted every tick so rows cover the ticks skipped by `interval`
+        for event in collision_reader.iter(&collision_events) {
+            let a = parent_query.get(event.a).ok().map(|x| x.0);
+            let b = parent_query.get(event.b).ok().map(|x| x.0);
+
+            for flock in a.iter().chain(b.iter().filter(|x| Some(**x) != a)) {
+                *collisions.entry(*flock).or_default() += 1;
+            }
+        }
+
+        let exiting = exit_reader.iter(&exit_events).next().is_some();
+
+        // Rows follow the simulation, a paused tick has nothing new to record
+        let stepped = clock.delta_seconds() > 0.0;
+        let tick = clock.ticks().saturating_sub(1);
+        let due = stepped && (tick % config.interval.max(1) as u64 == 0 || exiting);
+
+        let FlockMetricsWriter { writer, header_written, unflushed_rows } = &mut *output;
+        let writer = match writer {
+            Some(writer) => writer,
+            None => return
+        };
+
+        if due {
+            if !*header_written {
+                if config.format == FlockMetricsFormat::Csv {
+                    if let Err(error) = writeln!(writer, "{}", Self::header(&config)) {
+                        warn!("could not write flock metrics header: {}", error);
+                    }
+                }
+                *header_written = true;
+            }
+
+            let collect_members = config.metrics.iter().any(|x| matches!(x, FlockMetric::NearestNeighbor | FlockMetric::Collisions | FlockMetric::SpeedHistogram));
+
+            for (entity, children, stats) in query.iter() {
+                let mut positions = Vec::new();
+                let mut velocities = Vec::new();
+
+                if collect_members {
+                    for child in children.iter() {
+                        if let Ok((transform, velocity)) = child_query.get(*child) {
+                            positions.push(transform.translation.truncate());
+                            velocities.push(velocity.0);
+                        }
+                    }
+                }
+
+                let sample = FlockSample {
+                    polarization: stats.polarization,
+                    spread: stats.spread,
+                    nearest_neighbor: Self::nearest_neighbors(&positions, world_bounds.bounds),
+                    collisions: collisions.remove(&entity).unwrap_or_default(),
+                    speed_histogram: Self::speed_histogram(&velocities, config.speed_bin_width, config.speed_bins)
+                };
+
+                let row = match config.format {
+                    FlockMetricsFormat::Csv => Self::csv_row(&config, tick, clock.elapsed_seconds(), entity, stats, &sample),
+                    FlockMetricsFormat::JsonLines => Self::json_row(&config, tick, clock.elapsed_seconds(), entity, stats, &sample)
+                };
+
+                if let Err(error) = writeln!(writer, "{}", row) {
+                    warn!("could not write flock metrics: {}", error);
+                }
+                *unflushed_rows += 1;
+            }
+        }
+
+        if *unflushed_rows >= config.flush_rows.max(1) || (exiting && *unflushed_rows > 0) {
+            if let Err(error) = writer.flush() {
+                warn!("could not flush flock metrics: {}", error);
+            }
+            *unflushed_rows = 0;
+        }
+    }
+}
+
+impl Plugin for FlockMetricsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        let writer = match File::create(&self.0.path) {
+            Ok(file) => Some(BufWriter::new(file)),
+            Err(error) => {
+                warn!("could not create flock metrics file {:?}: {}", self.0.path, error);
+                None
+            }
+        };
+
+        app
+            .add_resource(self.0.clone())
+            .add_resource(FlockMetricsWriter { writer, header_written: false, unflushed_rows: 0 })
+            .init_resource::<SimulationClock>()
+            .init_resource::<WorldBounds>()
+            .init_resource::<Events<MemberCollision>>()
+            .init_resource::<Events<AppExit>>()
+            .add_system_to_stage(stage::LAST, Self::record.system());
+    }
+}
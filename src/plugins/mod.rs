@@ -1,9 +1,21 @@
+#[cfg(feature = "tools")]
+mod debug_draw;
+#[cfg(feature = "tools")]
+mod interaction;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "replay")]
+mod replay;
+#[cfg(feature = "tools")]
+mod tuning;
 
-mod fps;
-pub mod bidimensional;
-pub mod examples;
-
-#[cfg(feature = "web")]
-pub mod viewport_resize;
-
-pub use fps::*;
+#[cfg(feature = "tools")]
+pub use debug_draw::*;
+#[cfg(feature = "tools")]
+pub use interaction::*;
+#[cfg(feature = "metrics")]
+pub use metrics::*;
+#[cfg(feature = "replay")]
+pub use replay::*;
+#[cfg(feature = "tools")]
+pub use tuning::*;
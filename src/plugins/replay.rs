@@ -0,0 +1,435 @@
+use std::{ collections::{ HashMap, HashSet }, fs::File, io::{ self, BufWriter, Cursor, Read, Write }, path::PathBuf };
+
+use bevy::{ prelude::*, app::AppExit };
+
+use crate::bidimensional::{ FlockMemberMarker, Velocity };
+
+const REPLAY_MAGIC: &[u8; 4] = b"FLKR";
+const REPLAY_VERSION: u32 = 2;
+const NO_FLOCK: u64 = u64::MAX;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayMemberState {
+    pub id: u64,
+    pub translation: Vec3,
+    pub velocity: Vec2
+}
+
+/// A single recorded tick. `spawned` holds `(member id, flock id)` pairs, ids are `Entity::to_bits` so a reused
+/// entity id with a new generation is recorded as a new member.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReplayFrame {
+    pub spawned: Vec<(u64, u64)>,
+    pub despawned: Vec<u64>,
+    pub members: Vec<ReplayMemberState>
+}
+
+fn write_u32(writer: &mut impl Write, value: u32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_u64(writer: &mut impl Write, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn write_f32(writer: &mut impl Write, value: f32) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f32(reader: &mut impl Read) -> io::Result<f32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+impl ReplayFrame {
+    pub fn write(&self, writer: &mut impl Write) -> io::Result<()> {
+        write_u32(writer, self.spawned.len() as u32)?;
+        for (id, flock) in self.spawned.iter() {
+            write_u64(writer, *id)?;
+            write_u64(writer, *flock)?;
+        }
+
+        write_u32(writer, self.despawned.len() as u32)?;
+        for id in self.despawned.iter() {
+            write_u64(writer, *id)?;
+        }
+
+        write_u32(writer, self.members.len() as u32)?;
+        for member in self.members.iter() {
+            write_u64(writer, member.id)?;
+            write_f32(writer, member.translation.x)?;
+            write_f32(writer, member.translation.y)?;
+            write_f32(writer, member.translation.z)?;
+            write_f32(writer, member.velocity.x)?;
+            write_f32(writer, member.velocity.y)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read(reader: &mut impl Read) -> io::Result<Self> {
+        let mut frame = ReplayFrame::default();
+
+        for _ in 0..read_u32(reader)? {
+            frame.spawned.push((read_u64(reader)?, read_u64(reader)?));
+        }
+
+        for _ in 0..read_u32(reader)? {
+            frame.despawned.push(read_u64(reader)?);
+        }
+
+        for _ in 0..read_u32(reader)? {
+            let id = read_u64(reader)?;
+            let translation = Vec3::new(read_f32(reader)?, read_f32(reader)?, read_f32(reader)?);
+            let velocity = Vec2::new(read_f32(reader)?, read_f32(reader)?);
+            frame.members.push(ReplayMemberState { id, translation, velocity });
+        }
+
+        Ok(frame)
+    }
+}
+
+pub fn write_replay_header(writer: &mut impl Write) -> io::Result<()> {
+    writer.write_all(REPLAY_MAGIC)?;
+    write_u32(writer, REPLAY_VERSION)
+}
+
+pub fn read_replay(bytes: &[u8]) -> io::Result<Vec<ReplayFrame>> {
+    let mut reader = Cursor::new(bytes);
+
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != REPLAY_MAGIC {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "not a flock replay"));
+    }
+
+    let version = read_u32(&mut reader)?;
+    if version != REPLAY_VERSION {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("unsupported replay version {}", version)));
+    }
+
+    let mut frames = Vec::new();
+    while (reader.position() as usize) < bytes.len() {
+        frames.push(ReplayFrame::read(&mut reader)?);
+    }
+
+    Ok(frames)
+}
+
+/// Marks an entity spawned by the replay player, holding the member id it was recorded with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayMember(pub u64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayEvent {
+    MemberSpawned { entity: Entity, recorded_id: u64, flock: Option<u64> },
+    MemberDespawned { entity: Entity, recorded_id: u64 },
+    Finished
+}
+
+/// Playback position of the replay player. Systems may seek, step or pause by editing it.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ReplayPlayback {
+    pub tick: usize,
+    pub playing: bool,
+    frame_count: usize
+}
+
+impl ReplayPlayback {
+    pub fn frame_count(&self) -> usize {
+        self.frame_count
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.tick + 1 >= self.frame_count
+    }
+
+    pub fn seek(&mut self, tick: usize) {
+        self.tick = tick.min(self.frame_count.saturating_sub(1));
+    }
+
+    pub fn step_forward(&mut self) {
+        self.seek(self.tick + 1);
+    }
+
+    pub fn step_back(&mut self) {
+        self.seek(self.tick.saturating_sub(1));
+    }
+}
+
+struct ReplayRecorder {
+    writer: Option<BufWriter<File>>,
+    known: HashSet<u64>,
+    unflushed_frames: usize
+}
+
+struct ReplayPlayer {
+    frames: Vec<ReplayFrame>,
+    flocks: HashMap<u64, u64>,
+    entities: HashMap<u64, Entity>,
+    current: Option<usize>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayMode {
+    Record(PathBuf),
+    Replay(PathBuf)
+}
+
+/// Records member state to a replay file, or plays one back.
+///
+/// In replay mode the member transforms are driven from the file, so the app should not also add
+/// `FlockingPlugin` or `MovementPlugin`. Replayed members carry no sprite, an app that draws them should
+/// dress the entities of `ReplayEvent::MemberSpawned`.
+#[derive(Debug, Clone)]
+pub struct ReplayPlugin(ReplayMode);
+
+impl ReplayPlugin {
+    /// Frames recorded between flushes, the file is also flushed when the app exits.
+    const FLUSH_FRAMES: usize = 60;
+
+    pub fn record(path: impl Into<PathBuf>) -> Self {
+        Self(ReplayMode::Record(path.into()))
+    }
+
+    pub fn replay(path: impl Into<PathBuf>) -> Self {
+        Self(ReplayMode::Replay(path.into()))
+    }
+
+    fn record_system(
+        mut recorder: ResMut<ReplayRecorder>,
+        mut exit_reader: Local<EventReader<AppExit>>,
+        exit_events: Res<Events<AppExit>>,
+        query: Query<(Entity, &GlobalTransform, &Velocity, Option<&Parent>), With<FlockMemberMarker>>
+    ) {
+        let ReplayRecorder { writer, known, unflushed_frames } = &mut *recorder;
+        let writer = match writer {
+            Some(writer) => writer,
+            None => return
+        };
+
+        let mut frame = ReplayFrame::default();
+        let mut seen = HashSet::new();
+
+        for (entity, transform, velocity, parent) in query.iter() {
+            let id = entity.to_bits();
+
+            if known.insert(id) {
+                frame.spawned.push((id, parent.map(|x| x.0.to_bits()).unwrap_or(NO_FLOCK)));
+            }
+
+            seen.insert(id);
+            frame.members.push(ReplayMemberState {
+                id,
+                translation: transform.translation,
+                velocity: velocity.0
+            });
+        }
+
+        frame.despawned = known.difference(&seen).copied().collect();
+        for id in frame.despawned.iter() {
+            known.remove(id);
+        }
+
+        if let Err(error) = frame.write(writer) {
+            warn!("could not write replay frame: {}", error);
+        }
+        *unflushed_frames += 1;
+
+        if *unflushed_frames >= Self::FLUSH_FRAMES || exit_reader.iter(&exit_events).next().is_some() {
+            if let Err(error) = writer.flush() {
+                warn!("could not flush replay file: {}", error);
+            }
+            *unflushed_frames = 0;
+        }
+    }
+
+    fn replay_system(
+        commands: &mut Commands,
+        mut playback: ResMut<ReplayPlayback>,
+        mut player: ResMut<ReplayPlayer>,
+        mut events: ResMut<Events<ReplayEvent>>,
+        mut query: Query<(&mut Transform, &mut GlobalTransform, &mut Velocity), With<ReplayMember>>
+    ) {
+        if playback.playing && player.current.is_some() {
+            if playback.is_finished() {
+                playback.playing = false;
+                events.send(ReplayEvent::Finished);
+            } else {
+                playback.step_forward();
+            }
+        }
+
+        if player.current == Some(playback.tick) || playback.tick >= player.frames.len() {
+            return;
+        }
+
+        let ReplayPlayer { frames, flocks, entities, current } = &mut *player;
+        let frame = &frames[playback.tick];
+        *current = Some(playback.tick);
+
+        let present: HashSet<u64> = frame.members.iter().map(|x| x.id).collect();
+        let removed: Vec<u64> = entities.keys().filter(|x| !present.contains(x)).copied().collect();
+        for recorded_id in removed {
+            if let Some(entity) = entities.remove(&recorded_id) {
+                commands.despawn(entity);
+                events.send(ReplayEvent::MemberDespawned { entity, recorded_id });
+            }
+        }
+
+        // Replayed members are root entities, so `Transform` is what transform propagation copies to `GlobalTransform`
+        for member in frame.members.iter() {
+            let rotation = Quat::from_rotation_z(member.velocity.y.atan2(member.velocity.x));
+            let transform = Transform {
+                translation: member.translation,
+                rotation,
+                scale: Vec3::one()
+            };
+            let global_transform = GlobalTransform {
+                translation: member.translation,
+                rotation,
+                scale: Vec3::one()
+            };
+
+            match entities.get(&member.id) {
+                Some(entity) => {
+                    if let Ok((mut local, mut global, mut velocity)) = query.get_mut(*entity) {
+                        *local = transform;
+                        *global = global_transform;
+                        velocity.0 = member.velocity;
+                    }
+                },
+                None => {
+                    let entity = commands
+                        .spawn((FlockMemberMarker, Velocity(member.velocity), global_transform, transform, ReplayMember(member.id)))
+                        .current_entity()
+                        .unwrap();
+
+                    entities.insert(member.id, entity);
+                    events.send(ReplayEvent::MemberSpawned {
+                        entity,
+                        recorded_id: member.id,
+                        flock: flocks.get(&member.id).copied().filter(|x| *x != NO_FLOCK)
+                    });
+                }
+            }
+        }
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        match &self.0 {
+            ReplayMode::Record(path) => {
+                let writer = File::create(path)
+                    .map(BufWriter::new)
+                    .and_then(|mut writer| write_replay_header(&mut writer).map(|_| writer));
+
+                let writer = match writer {
+                    Ok(writer) => Some(writer),
+                    Err(error) => {
+                        warn!("could not create replay file {:?}: {}", path, error);
+                        None
+                    }
+                };
+
+                app
+                    .add_resource(ReplayRecorder { writer, known: HashSet::new(), unflushed_frames: 0 })
+                    .init_resource::<Events<AppExit>>()
+                    .add_system_to_stage(stage::LAST, Self::record_system.system());
+            },
+            ReplayMode::Replay(path) => {
+                let frames = match std::fs::read(path).and_then(|x| read_replay(&x)) {
+                    Ok(frames) => frames,
+                    Err(error) => {
+                        warn!("could not read replay file {:?}: {}", path, error);
+                        Vec::new()
+                    }
+                };
+
+                // Flocks only appear in the frame a member spawned in, so look them up once for any seek
+                let flocks = frames.iter().flat_map(|x| x.spawned.iter()).copied().collect();
+
+                app
+                    .add_resource(ReplayPlayback {
+                        tick: 0,
+                        playing: true,
+                        frame_count: frames.len()
+                    })
+                    .add_resource(ReplayPlayer {
+                        frames,
+                        flocks,
+                        entities: HashMap::new(),
+                        current: None
+                    })
+                    .add_event::<ReplayEvent>()
+                    .add_system(Self::replay_system.system());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frames() -> Vec<ReplayFrame> {
+        let member = |id: u64, x: f32| ReplayMemberState {
+            id,
+            translation: Vec3::new(x, -x * 0.5, 3.0),
+            velocity: Vec2::new(x.sin(), x.cos())
+        };
+
+        vec![
+            ReplayFrame {
+                spawned: vec![(1, 10), (2 | 1 << 32, NO_FLOCK)],
+                despawned: vec![],
+                members: vec![member(1, 0.1), member(2 | 1 << 32, -123.456)]
+            },
+            ReplayFrame {
+                spawned: vec![],
+                despawned: vec![2 | 1 << 32],
+                members: vec![member(1, 1e-7)]
+            }
+        ]
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let mut bytes = Vec::new();
+        write_replay_header(&mut bytes).unwrap();
+        for frame in frames() {
+            frame.write(&mut bytes).unwrap();
+        }
+
+        assert_eq!(read_replay(&bytes).unwrap(), frames());
+    }
+
+    #[test]
+    fn bad_headers_are_rejected() {
+        let mut bytes = Vec::new();
+        write_replay_header(&mut bytes).unwrap();
+        frames()[0].write(&mut bytes).unwrap();
+
+        let mut bad_magic = bytes.clone();
+        bad_magic[0] = b'X';
+        assert_eq!(read_replay(&bad_magic).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut bad_version = bytes.clone();
+        bad_version[4..8].copy_from_slice(&(REPLAY_VERSION + 1).to_le_bytes());
+        assert_eq!(read_replay(&bad_version).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,532 @@
+use std::collections::HashMap;
+
+use bevy::{ prelude::*, ui::FocusPolicy, window::ReceivedCharacter };
+
+use crate::{ bidimensional::{ Flock, FlockMemberParams, SimulationClock }, ui_camera::UiCamera };
+
+#[derive(Clone, Debug)]
+pub struct TuningPanelConfig {
+    pub font: &'static str,
+    pub text_style: TextStyle,
+    pub style: Style,
+    pub toggle_key: KeyCode
+}
+
+impl Default for TuningPanelConfig {
+    fn default() -> Self {
+        TuningPanelConfig {
+            font: "fonts/Inconsolata.ttf",
+            text_style: TextStyle {
+                font_size: 16.0,
+                color: Color::WHITE,
+                ..Default::default()
+            },
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(1.0),
+                    right: Val::Px(1.0),
+                    ..Default::default()
+                },
+                flex_direction: FlexDirection::ColumnReverse,
+                padding: Rect::all(Val::Px(4.0)),
+                ..Default::default()
+            },
+            toggle_key: KeyCode::F1
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum TuningField {
+    FlockRadius,
+    AlignmentStrength,
+    CohesionStrength,
+    SeparationStrength,
+    MaxSpeed,
+    MaxAccel,
+    SafeRadius
+}
+
+impl TuningField {
+    const ALL: [TuningField; 7] = [
+        TuningField::FlockRadius,
+        TuningField::AlignmentStrength,
+        TuningField::CohesionStrength,
+        TuningField::SeparationStrength,
+        TuningField::MaxSpeed,
+        TuningField::MaxAccel,
+        TuningField::SafeRadius
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            TuningField::FlockRadius => "radius",
+            TuningField::AlignmentStrength => "align",
+            TuningField::CohesionStrength => "cohere",
+            TuningField::SeparationStrength => "separate",
+            TuningField::MaxSpeed => "speed",
+            TuningField::MaxAccel => "accel",
+            TuningField::SafeRadius => "safe"
+        }
+    }
+
+    /// Slider range and the increment of the -/+ buttons.
+    fn range(&self) -> (f32, f32, f32) {
+        match self {
+            TuningField::FlockRadius => (0.0, 300.0, 5.0),
+            TuningField::AlignmentStrength | TuningField::CohesionStrength | TuningField::SeparationStrength => (0.0, 5.0, 0.1),
+            TuningField::MaxSpeed => (0.0, 500.0, 10.0),
+            TuningField::MaxAccel => (0.0, 300.0, 5.0),
+            TuningField::SafeRadius => (0.0, 200.0, 5.0)
+        }
+    }
+
+    fn is_member_field(&self) -> bool {
+        matches!(self, TuningField::MaxSpeed | TuningField::MaxAccel | TuningField::SafeRadius)
+    }
+
+    fn get(&self, flock: &Flock, params: &FlockMemberParams) -> f32 {
+        match self {
+            TuningField::FlockRadius => flock.flock_radius,
+            TuningField::AlignmentStrength => flock.alignment_strength,
+            TuningField::CohesionStrength => flock.cohesion_strength,
+            TuningField::SeparationStrength => flock.separation_strength,
+            TuningField::MaxSpeed => params.max_speed,
+            TuningField::MaxAccel => params.max_accel,
+            TuningField::SafeRadius => params.safe_radius
+        }
+    }
+
+    fn set_flock(&self, flock: &mut Flock, value: f32) {
+        match self {
+            TuningField::FlockRadius => flock.flock_radius = value,
+            TuningField::AlignmentStrength => flock.alignment_strength = value,
+            TuningField::CohesionStrength => flock.cohesion_strength = value,
+            TuningField::SeparationStrength => flock.separation_strength = value,
+            _ => {}
+        }
+    }
+
+    fn set_member(&self, params: &mut FlockMemberParams, value: f32) {
+        match self {
+            TuningField::MaxSpeed => params.max_speed = value,
+            TuningField::MaxAccel => params.max_accel = value,
+            TuningField::SafeRadius => params.safe_radius = value,
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TuningAction {
+    PreviousFlock,
+    NextFlock,
+    Pause,
+    Step,
+    Reset,
+    Decrease(TuningField),
+    Increase(TuningField),
+    Edit(TuningField)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TuningText {
+    Flock,
+    Pause,
+    Value(TuningField)
+}
+
+struct TuningPanelMarker;
+struct TuningSlider(TuningField);
+struct TuningSliderFill(TuningField);
+struct TuningButton(TuningAction);
+
+/// Set while a value field of the panel has keyboard focus, so other key bindings can ignore the keys typed into it.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TuningPanelFocus(pub bool);
+
+#[derive(Debug, Default)]
+struct TuningState {
+    selected: usize,
+    cursor: Vec2,
+    /// Slider held down, it keeps following the cursor until the button is released.
+    dragging: Option<TuningField>,
+    /// Value field being typed into and its text so far.
+    editing: Option<(TuningField, String)>,
+    original_flocks: HashMap<Entity, Flock>,
+    original_members: HashMap<Entity, FlockMemberParams>
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct TuningPanelPlugin(TuningPanelConfig);
+
+impl TuningPanelPlugin {
+    pub fn new(config: TuningPanelConfig) -> Self {
+        Self(config)
+    }
+
+    fn button(parent: &mut ChildBuilder, font: &Handle<Font>, text_style: &TextStyle, material: &Handle<ColorMaterial>, label: &str, action: TuningAction, text: Option<TuningText>) {
+        parent
+            .spawn(ButtonBundle {
+                style: Style {
+                    margin: Rect::all(Val::Px(1.0)),
+                    padding: Rect {
+                        left: Val::Px(4.0),
+                        right: Val::Px(4.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                material: material.clone(),
+                ..Default::default()
+            })
+            .with(TuningButton(action))
+            .with_children(|button| {
+                button.spawn(TextBundle {
+                    text: Text {
+                        value: label.to_string(),
+                        font: font.clone(),
+                        style: text_style.clone(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+
+                if let Some(text) = text {
+                    button.with(text);
+                }
+            });
+    }
+
+    fn text(parent: &mut ChildBuilder, font: &Handle<Font>, text_style: &TextStyle, value: &str, width: f32, text: Option<TuningText>) {
+        parent.spawn(TextBundle {
+            style: Style {
+                size: Size::new(Val::Px(width), Val::Auto),
+                margin: Rect::all(Val::Px(1.0)),
+                ..Default::default()
+            },
+            text: Text {
+                value: value.to_string(),
+                font: font.clone(),
+                style: text_style.clone(),
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        if let Some(text) = text {
+            parent.with(text);
+        }
+    }
+
+    fn tuning_setup(commands: &mut Commands, config: Res<TuningPanelConfig>, asset_server: Res<AssetServer>, mut materials: ResMut<Assets<ColorMaterial>>, mut ui_camera: ResMut<UiCamera>) {
+        let font: Handle<Font> = asset_server.load(config.font);
+        let text_style = config.text_style.clone();
+        let panel = materials.add(Color::rgba(0.0, 0.0, 0.0, 0.6).into());
+        let button = materials.add(Color::rgb(0.25, 0.25, 0.25).into());
+        let track = materials.add(Color::rgb(0.15, 0.15, 0.15).into());
+        let fill = materials.add(Color::rgb(0.4, 0.6, 0.9).into());
+        let value = materials.add(Color::rgb(0.1, 0.1, 0.1).into());
+        let row = materials.add(Color::rgba(0.0, 0.0, 0.0, 0.0).into());
+        let row_style = Style {
+            flex_direction: FlexDirection::Row,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        };
+
+        ui_camera.spawn(commands);
+        commands
+            .spawn(NodeBundle {
+                style: config.style.clone(),
+                material: panel,
+                ..Default::default()
+            })
+            .with(TuningPanelMarker)
+            .with_children(|parent| {
+                parent
+                    .spawn(NodeBundle { style: row_style.clone(), material: row.clone(), ..Default::default() })
+                    .with_children(|parent| {
+                        Self::button(parent, &font, &text_style, &button, "<", TuningAction::PreviousFlock, None);
+                        Self::text(parent, &font, &text_style, "flock", 160.0, Some(TuningText::Flock));
+                        Self::button(parent, &font, &text_style, &button, ">", TuningAction::NextFlock, None);
+                    });
+
+                for field in TuningField::ALL.iter().copied() {
+                    parent
+                        .spawn(NodeBundle { style: row_style.clone(), material: row.clone(), ..Default::default() })
+                        .with_children(|parent| {
+                            Self::text(parent, &font, &text_style, field.label(), 80.0, None);
+                            Self::button(parent, &font, &text_style, &button, "-", TuningAction::Decrease(field), None);
+
+                            parent
+                                .spawn(ButtonBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(120.0), Val::Px(14.0)),
+                                        margin: Rect::all(Val::Px(1.0)),
+                                        ..Default::default()
+                                    },
+                                    material: track.clone(),
+                                    ..Default::default()
+                                })
+                                .with(TuningSlider(field))
+                                .with_children(|parent| {
+                                    parent
+                                        .spawn(NodeBundle {
+                                            style: Style {
+                                                size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                                                ..Default::default()
+                                            },
+                                            material: fill.clone(),
+                                            ..Default::default()
+                                        })
+                                        .with(TuningSliderFill(field))
+                                        .with(FocusPolicy::Pass);
+                                });
+
+                            Self::button(parent, &font, &text_style, &button, "+", TuningAction::Increase(field), None);
+
+                            parent
+                                .spawn(ButtonBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(60.0), Val::Auto),
+                                        margin: Rect::all(Val::Px(1.0)),
+                                        ..Default::default()
+                                    },
+                                    material: value.clone(),
+                                    ..Default::default()
+                                })
+                                .with(TuningButton(TuningAction::Edit(field)))
+                                .with_children(|parent| {
+                                    Self::text(parent, &font, &text_style, "", 56.0, Some(TuningText::Value(field)));
+                                });
+                        });
+                }
+
+                parent
+                    .spawn(NodeBundle { style: row_style.clone(), material: row.clone(), ..Default::default() })
+                    .with_children(|parent| {
+                        Self::button(parent, &font, &text_style, &button, "pause", TuningAction::Pause, Some(TuningText::Pause));
+                        Self::button(parent, &font, &text_style, &button, "step", TuningAction::Step, None);
+                        Self::button(parent, &font, &text_style, &button, "reset", TuningAction::Reset, None);
+                    });
+            });
+    }
+
+    fn mean_params<'a>(members: impl Iterator<Item = &'a FlockMemberParams>) -> FlockMemberParams {
+        let mut mean = FlockMemberParams::default();
+        let mut count = 0;
+
+        for params in members {
+            mean.max_speed += params.max_speed;
+            mean.max_accel += params.max_accel;
+            mean.safe_radius += params.safe_radius;
+            count += 1;
+        }
+
+        if count > 0 {
+            mean.max_speed /= count as f32;
+            mean.max_accel /= count as f32;
+            mean.safe_radius /= count as f32;
+        }
+
+        mean
+    }
+
+    /// Sets `field` on the flock, or on its members. Member values are scaled so their mean matches `value`,
+    /// which keeps the variation between members.
+    fn set_field(state: &mut TuningState, field: TuningField, value: f32, entity: Entity, flock: &mut Flock, children: &Children, member_query: &mut Query<&mut FlockMemberParams>) {
+        let (min, max, _) = field.range();
+        let value = value.max(min).min(max);
+
+        if !field.is_member_field() {
+            state.original_flocks.entry(entity).or_insert(*flock);
+            field.set_flock(flock, value);
+            return;
+        }
+
+        let mean = field.get(flock, &Self::mean_params(children.iter().filter_map(|x| member_query.get_component::<FlockMemberParams>(*x).ok())));
+
+        for child in children.iter() {
+            if let Ok(mut params) = member_query.get_mut(*child) {
+                state.original_members.entry(*child).or_insert(*params);
+
+                let current = field.get(flock, &params);
+                field.set_member(&mut params, if mean > 0.0 { current * value / mean } else { value });
+            }
+        }
+    }
+
+    fn tuning_interaction(
+        mut state: ResMut<TuningState>,
+        mut focus: ResMut<TuningPanelFocus>,
+        mut clock: ResMut<SimulationClock>,
+        mouse: Res<Input<MouseButton>>,
+        keys: Res<Input<KeyCode>>,
+        mut cursor_reader: Local<EventReader<CursorMoved>>,
+        cursor_events: Res<Events<CursorMoved>>,
+        mut character_reader: Local<EventReader<ReceivedCharacter>>,
+        character_events: Res<Events<ReceivedCharacter>>,
+        button_query: Query<(&Interaction, &TuningButton), Mutated<Interaction>>,
+        slider_query: Query<(&Interaction, &TuningSlider, &Node, &GlobalTransform)>,
+        mut flock_query: Query<(Entity, &mut Flock, &Children)>,
+        mut member_query: Query<&mut FlockMemberParams>
+    ) {
+        if let Some(event) = cursor_reader.latest(&cursor_events) {
+            state.cursor = event.position;
+        }
+
+        let mut flocks: Vec<Entity> = flock_query.iter_mut().map(|(entity, _, _)| entity).collect();
+        flocks.sort_by_key(|x| x.id());
+
+        let mut edits = Vec::new();
+
+        for (interaction, button) in button_query.iter() {
+            if *interaction != Interaction::Clicked {
+                continue;
+            }
+
+            match button.0 {
+                TuningAction::PreviousFlock => state.selected = (state.selected + flocks.len().max(1) - 1) % flocks.len().max(1),
+                TuningAction::NextFlock => state.selected = (state.selected + 1) % flocks.len().max(1),
+                TuningAction::Pause => clock.toggle_pause(),
+                TuningAction::Step => clock.step(),
+                TuningAction::Reset => {
+                    for (entity, mut flock, children) in flock_query.iter_mut() {
+                        if let Some(original) = state.original_flocks.remove(&entity) {
+                            *flock = original;
+                        }
+
+                        for child in children.iter() {
+                            if let (Some(original), Ok(mut params)) = (state.original_members.remove(child), member_query.get_mut(*child)) {
+                                *params = original;
+                            }
+                        }
+                    }
+                },
+                TuningAction::Decrease(field) => edits.push((field, -field.range().2)),
+                TuningAction::Increase(field) => edits.push((field, field.range().2)),
+                TuningAction::Edit(field) => state.editing = Some((field, String::new()))
+            }
+        }
+
+        // Typing into a value field, Return applies it and Escape discards it
+        let typed: Vec<char> = character_reader.iter(&character_events).map(|x| x.char).collect();
+        let mut typed_value = None;
+
+        if let Some((field, mut text)) = state.editing.take() {
+            text.extend(typed.into_iter().filter(|x| x.is_ascii_digit() || *x == '.' || *x == '-'));
+
+            if keys.just_pressed(KeyCode::Back) {
+                text.pop();
+            }
+
+            if keys.just_pressed(KeyCode::Return) {
+                typed_value = text.parse::<f32>().ok().map(|x| (field, x));
+            } else if !keys.just_pressed(KeyCode::Escape) {
+                state.editing = Some((field, text));
+            }
+        }
+
+        focus.0 = state.editing.is_some();
+
+        if !mouse.pressed(MouseButton::Left) {
+            state.dragging = None;
+        }
+
+        for (interaction, slider, _, _) in slider_query.iter() {
+            if *interaction == Interaction::Clicked {
+                state.dragging = Some(slider.0);
+            }
+        }
+
+        let selected = match flocks.get(state.selected % flocks.len().max(1)) {
+            Some(selected) => *selected,
+            None => return
+        };
+
+        if let Ok((entity, mut flock, children)) = flock_query.get_mut(selected) {
+            for (field, delta) in edits {
+                let current = field.get(&flock, &Self::mean_params(children.iter().filter_map(|x| member_query.get_component::<FlockMemberParams>(*x).ok())));
+                Self::set_field(&mut state, field, current + delta, entity, &mut flock, children, &mut member_query);
+            }
+
+            if let Some((field, value)) = typed_value {
+                Self::set_field(&mut state, field, value, entity, &mut flock, children, &mut member_query);
+            }
+
+            for (_, slider, node, transform) in slider_query.iter() {
+                if state.dragging == Some(slider.0) && node.size.x > 0.0 {
+                    let (min, max, _) = slider.0.range();
+                    let left = transform.translation.x - node.size.x / 2.0;
+                    let fraction = ((state.cursor.x - left) / node.size.x).max(0.0).min(1.0);
+
+                    Self::set_field(&mut state, slider.0, min + fraction * (max - min), entity, &mut flock, children, &mut member_query);
+                }
+            }
+        }
+    }
+
+    fn tuning_display(
+        state: Res<TuningState>,
+        clock: Res<SimulationClock>,
+        flock_query: Query<(Entity, &Flock, &Children)>,
+        member_query: Query<&FlockMemberParams>,
+        mut text_query: Query<(&mut Text, &TuningText)>,
+        mut fill_query: Query<(&mut Style, &TuningSliderFill)>
+    ) {
+        let mut flocks: Vec<(Entity, &Flock, &Children)> = flock_query.iter().collect();
+        flocks.sort_by_key(|(entity, _, _)| entity.id());
+
+        let selected = flocks.get(state.selected % flocks.len().max(1));
+        let values = selected.map(|(_, flock, children)| (**flock, Self::mean_params(children.iter().filter_map(|x| member_query.get(*x).ok()))));
+
+        let editing = state.editing.as_ref();
+
+        for (mut text, tuning_text) in text_query.iter_mut() {
+            text.value = match (tuning_text, &selected, &values) {
+                (TuningText::Value(field), _, _) if editing.map(|x| x.0) == Some(*field) => format!("{}_", editing.map(|x| x.1.as_str()).unwrap_or_default()),
+                (TuningText::Flock, Some((entity, _, _)), _) => format!("flock {} ({}/{})", entity.id(), state.selected % flocks.len() + 1, flocks.len()),
+                (TuningText::Flock, None, _) => "no flocks".to_string(),
+                (TuningText::Pause, _, _) => format!("{} x{}", if clock.paused { "resume" } else { "pause" }, clock.time_scale),
+                (TuningText::Value(field), _, Some((flock, params))) => format!("{:.2}", field.get(flock, params)),
+                (TuningText::Value(_), _, None) => "-".to_string()
+            };
+        }
+
+        for (mut style, fill) in fill_query.iter_mut() {
+            let (min, max, _) = fill.0.range();
+            let fraction = match &values {
+                Some((flock, params)) => ((fill.0.get(flock, params) - min) / (max - min)).max(0.0).min(1.0),
+                None => 0.0
+            };
+
+            style.size.width = Val::Percent(fraction * 100.0);
+        }
+    }
+
+    fn tuning_toggle(config: Res<TuningPanelConfig>, keys: Res<Input<KeyCode>>, mut query: Query<&mut Style, With<TuningPanelMarker>>) {
+        if keys.just_pressed(config.toggle_key) {
+            for mut style in query.iter_mut() {
+                style.display = match style.display {
+                    Display::None => Display::Flex,
+                    _ => Display::None
+                };
+            }
+        }
+    }
+}
+
+impl Plugin for TuningPanelPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app
+            .add_resource(self.0.clone())
+            .init_resource::<TuningState>()
+            .init_resource::<TuningPanelFocus>()
+            .init_resource::<UiCamera>()
+            .add_startup_system(Self::tuning_setup.system())
+            .add_system(Self::tuning_interaction.system())
+            .add_system(Self::tuning_display.system())
+            .add_system(Self::tuning_toggle.system());
+    }
+}
@@ -0,0 +1,13 @@
+use bevy::prelude::*;
+
+/// The UI camera shared by the overlay plugins, spawned by whichever of them sets up first.
+#[derive(Debug, Default)]
+pub(crate) struct UiCamera(Option<Entity>);
+
+impl UiCamera {
+    pub(crate) fn spawn(&mut self, commands: &mut Commands) {
+        if self.0.is_none() {
+            self.0 = commands.spawn(CameraUiBundle::default()).current_entity();
+        }
+    }
+}
@@ -21,24 +21,97 @@ pub trait BoundTo {
     fn bound_to(self, center: Self::Item, bounds: Bounds<Self::Item>) -> Self::Item;
 }
 
+impl<T: Copy> Bounds<T> {
+    pub fn new(lower: T, upper: T) -> Self {
+        Bounds {
+            lower,
+            upper
+        }
+    }
+
+    pub fn lower(&self) -> T {
+        self.lower
+    }
+
+    pub fn upper(&self) -> T {
+        self.upper
+    }
+}
+
+/// Wraps `value` into `[lower, upper]` however many times it is out of it.
+#[inline]
+fn wrap(value: f32, lower: f32, upper: f32) -> f32 {
+    let size = upper - lower;
+
+    if (value >= lower && value <= upper) || size <= 0.0 {
+        return value;
+    }
+
+    // rem_euclid can round up to `size` for values just below a multiple of it
+    (lower + (value - lower).rem_euclid(size)).min(upper)
+}
+
 impl BoundTo for Vec2 {
     type Item = Vec2;
 
     fn bound_to(self, center: Vec2, bounds: Bounds<Vec2>) -> Vec2 {
-        let mut new: Vec2 = Vec2::new(self.x, self.y) - center;
+        let new: Vec2 = Vec2::new(self.x, self.y) - center;
 
-        if new.x < bounds.lower.x {
-            new.x = new.x + bounds.upper.x - bounds.lower.x;
-        } else if new.x > bounds.upper.x {
-            new.x = new.x - bounds.upper.x + bounds.lower.x;
-        }
+        Vec2::new(
+            wrap(new.x, bounds.lower.x, bounds.upper.x),
+            wrap(new.y, bounds.lower.y, bounds.upper.y)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::prelude::*;
+
+    use super::*;
+
+    fn bounds() -> impl Strategy<Value = Bounds<Vec2>> {
+        (10.0f32..4000.0, 10.0f32..4000.0).prop_map(|(width, height)| Bounds::new(Vec2::new(-width / 2.0, -height / 2.0), Vec2::new(width / 2.0, height / 2.0)))
+    }
+
+    fn point() -> impl Strategy<Value = Vec2> {
+        (-1e5f32..1e5, -1e5f32..1e5).prop_map(|(x, y)| Vec2::new(x, y))
+    }
+
+    #[test]
+    fn wraps_multiple_times() {
+        let bounds = Bounds::new(Vec2::new(-50.0, -50.0), Vec2::new(50.0, 50.0));
 
-        if new.y < bounds.lower.y {
-            new.y = new.y + bounds.upper.y - bounds.lower.y;
-        } else if new.y > bounds.upper.y {
-            new.y = new.y - bounds.upper.y + bounds.lower.y;
+        assert_eq!(Vec2::new(260.0, -330.0).bound_to(Vec2::zero(), bounds), Vec2::new(-40.0, -30.0));
+        assert_eq!(Vec2::new(20.0, 0.0).bound_to(Vec2::new(-310.0, 0.0), bounds), Vec2::new(30.0, 0.0));
+    }
+
+    #[test]
+    fn keeps_points_inside() {
+        let bounds = Bounds::new(Vec2::new(-50.0, -50.0), Vec2::new(50.0, 50.0));
+
+        assert_eq!(Vec2::new(50.0, -50.0).bound_to(Vec2::zero(), bounds), Vec2::new(50.0, -50.0));
+        assert_eq!(Vec2::new(10.0, 20.0).bound_to(Vec2::new(5.0, 5.0), bounds), Vec2::new(5.0, 15.0));
+    }
+
+    proptest! {
+        #[test]
+        fn bound_to_lands_within_bounds(position in point(), center in point(), bounds in bounds()) {
+            let bounded = position.bound_to(center, bounds);
+
+            prop_assert!(bounded.x >= bounds.lower().x && bounded.x <= bounds.upper().x);
+            prop_assert!(bounded.y >= bounds.lower().y && bounded.y <= bounds.upper().y);
         }
 
-        new
+        #[test]
+        fn bound_to_only_shifts_by_whole_sizes(position in point(), center in point(), bounds in bounds()) {
+            let size = bounds.upper() - bounds.lower();
+            let shift = (position - center) - position.bound_to(center, bounds);
+            let periods = shift / size;
+
+            // Allow for the precision lost wrapping values far out of bounds
+            prop_assert!((periods.x - periods.x.round()).abs() < 1e-2);
+            prop_assert!((periods.y - periods.y.round()).abs() < 1e-2);
+        }
     }
 }
@@ -0,0 +1,271 @@
+use bevy::prelude::*;
+
+use bevy_test::{ headless::*, bidimensional::* };
+
+#[test]
+fn runs_are_deterministic() {
+    let description = FlockDescription::ring(Vec2::new(50.0, -20.0), 80.0, 30).with_velocity(Vec2::new(40.0, 10.0));
+    let run = || {
+        let mut simulation = HeadlessSimulation::new(800.0, 600.0);
+        let (_, members) = simulation.spawn_flock(&description);
+        simulation.step(120);
+        members.iter().map(|x| simulation.position(*x)).collect::<Vec<Vec2>>()
+    };
+
+    assert_eq!(run(), run());
+}
+
+#[test]
+fn members_wrap_within_bounds() {
+    let mut simulation = HeadlessSimulation::new(400.0, 300.0);
+    let (_, members) = simulation.spawn_flock(&FlockDescription::grid(Vec2::zero(), 10, 10, 20.0).with_velocity(Vec2::new(150.0, 80.0)));
+    simulation.step(300);
+
+    let bounds = simulation.bounds().bounds;
+    for member in members {
+        let position = simulation.position(member);
+        assert!(position.x >= bounds.lower().x && position.x <= bounds.upper().x, "{:?} is out of bounds", position);
+        assert!(position.y >= bounds.lower().y && position.y <= bounds.upper().y, "{:?} is out of bounds", position);
+    }
+}
+
+#[test]
+fn stats_count_members() {
+    let mut simulation = HeadlessSimulation::new(800.0, 600.0);
+    let (flock, _) = simulation.spawn_flock(&FlockDescription::grid(Vec2::new(100.0, 50.0), 5, 4, 30.0));
+
+    assert_eq!(simulation.stats(flock), None);
+    simulation.step(1);
+    assert_eq!(simulation.stats(flock).map(|x| x.member_count), Some(20));
+}
+
+#[test]
+fn separation_pushes_members_apart() {
+    let flock = Flock {
+        flock_radius: 50.0,
+        alignment_strength: 0.0,
+        cohesion_strength: 0.0,
+        separation_strength: 1.0
+    };
+
+    let mut simulation = HeadlessSimulation::new(800.0, 600.0);
+    let (_, members) = simulation.spawn_flock(&FlockDescription::at(&[Vec2::new(-5.0, 0.0), Vec2::new(5.0, 0.0)]).with_flock(flock));
+    simulation.step(30);
+
+    assert!(simulation.position(members[0]).x < -5.0);
+    assert!(simulation.position(members[1]).x > 5.0);
+}
+
+#[test]
+fn paused_clock_freezes_members() {
+    let mut simulation = HeadlessSimulation::new(800.0, 600.0);
+    let (_, members) = simulation.spawn_flock(&FlockDescription::ring(Vec2::zero(), 60.0, 12).with_velocity(Vec2::new(100.0, 0.0)));
+
+    let mut clock = simulation.clock();
+    clock.paused = true;
+    simulation.set_clock(clock);
+
+    let before: Vec<Vec2> = members.iter().map(|x| simulation.position(*x)).collect();
+    simulation.step(10);
+    let after: Vec<Vec2> = members.iter().map(|x| simulation.position(*x)).collect();
+
+    assert_eq!(before, after);
+}
+
+#[test]
+fn time_scale_slows_members() {
+    let travelled = |time_scale: f32| {
+        let mut simulation = HeadlessSimulation::new(800.0, 600.0);
+        let description = FlockDescription::at(&[Vec2::zero()])
+            .with_flock(Flock {
+                flock_radius: 50.0,
+                alignment_strength: 0.0,
+                cohesion_strength: 0.0,
+                separation_strength: 0.0
+            })
+            .with_velocity(Vec2::new(60.0, 0.0));
+        let (_, members) = simulation.spawn_flock(&description);
+
+        let mut clock = simulation.clock();
+        clock.time_scale = time_scale;
+        simulation.set_clock(clock);
+
+        simulation.step(10);
+        simulation.position(members[0]).x
+    };
+
+    assert!((travelled(0.5) * 2.0 - travelled(1.0)).abs() < 0.01);
+}
+
+#[test]
+fn spawned_runs_follow_the_seed() {
+    let run = |seed: u64| {
+        let mut simulation = HeadlessSimulation::with_plugins(800.0, 600.0, |app| {
+            app
+                .add_resource(SimRng::new(seed))
+                .add_plugin(FlockSpawnerPlugin);
+        });
+
+        let flock = simulation.app.world.spawn((Flock {
+            flock_radius: 50.0,
+            alignment_strength: 1.0,
+            cohesion_strength: 1.0,
+            separation_strength: 1.0
+        }, FlockSpawner {
+            count: 40,
+            shape: SpawnShape::Disc { radius: 150.0 },
+            initial_velocity: ParamDistribution::Uniform(-50.0, 50.0),
+            ..Default::default()
+        }));
+
+        simulation.step(120);
+        let members: Vec<Entity> = simulation.app.world.get::<Children>(flock).unwrap().iter().copied().collect();
+        members.iter().map(|x| simulation.position(*x)).collect::<Vec<Vec2>>()
+    };
+
+    assert_eq!(run(7), run(7));
+    assert_ne!(run(7), run(8));
+}
+
+fn still_flock() -> Flock {
+    Flock {
+        flock_radius: 50.0,
+        alignment_strength: 0.0,
+        cohesion_strength: 0.0,
+        separation_strength: 0.0
+    }
+}
+
+fn clustering_simulation() -> HeadlessSimulation {
+    HeadlessSimulation::with_plugins(1600.0, 1200.0, |app| {
+        app.add_plugin(FlockClusteringPlugin::new(FlockClusteringConfig {
+            interval: 2,
+            ..Default::default()
+        }));
+    })
+}
+
+/// Member counts of every flock, largest first.
+fn flock_sizes(simulation: &HeadlessSimulation) -> Vec<usize> {
+    let mut sizes: Vec<usize> = simulation.app.world.query::<(&Flock, &Children)>().map(|(_, children)| children.len()).collect();
+    sizes.sort_by(|a, b| b.cmp(a));
+    sizes
+}
+
+#[test]
+fn far_apart_groups_split() {
+    let mut simulation = clustering_simulation();
+    let mut positions = FlockDescription::grid(Vec2::new(-400.0, 0.0), 3, 2, 20.0).positions;
+    positions.extend(FlockDescription::grid(Vec2::new(400.0, 0.0), 2, 2, 20.0).positions);
+    let (flock, members) = simulation.spawn_flock(&FlockDescription::at(&positions).with_flock(still_flock()));
+    simulation.app.world.insert_one(flock, Formation::new(FormationShape::Line { spacing: 20.0 })).unwrap();
+
+    simulation.step(3);
+
+    assert_eq!(flock_sizes(&simulation), vec![6, 4]);
+
+    let split = simulation.app.world.get::<Parent>(members[9]).unwrap().0;
+    assert_ne!(split, flock);
+    assert!(simulation.app.world.get::<Formation>(split).is_ok());
+}
+
+#[test]
+fn overlapping_flocks_merge() {
+    let mut simulation = clustering_simulation();
+    simulation.spawn_flock(&FlockDescription::grid(Vec2::zero(), 3, 3, 20.0).with_flock(still_flock()));
+    simulation.spawn_flock(&FlockDescription::grid(Vec2::new(10.0, 10.0), 2, 2, 20.0).with_flock(still_flock()));
+
+    simulation.step(3);
+
+    assert_eq!(flock_sizes(&simulation), vec![13]);
+}
+
+fn collision_simulation() -> HeadlessSimulation {
+    HeadlessSimulation::with_plugins(800.0, 600.0, |app| {
+        app.add_plugin(MemberCollisionPlugin::new(MemberCollisionConfig {
+            radius_scale: 0.2,
+            restitution: 0.5,
+            between_flocks: true
+        }));
+    })
+}
+
+#[test]
+fn overlapping_members_bounce_apart() {
+    let mut simulation = collision_simulation();
+    let (_, members) = simulation.spawn_flock(&FlockDescription::at(&[Vec2::new(-6.0, 0.0), Vec2::new(6.0, 0.0)]).with_flock(still_flock()));
+    simulation.app.world.get_mut::<Velocity>(members[0]).unwrap().0 = Vec2::new(20.0, 0.0);
+    simulation.app.world.get_mut::<Velocity>(members[1]).unwrap().0 = Vec2::new(-20.0, 0.0);
+
+    simulation.step(1);
+
+    // Radii of 10 each, closing at 40 and leaving at half of that
+    let distance = (simulation.position(members[1]) - simulation.position(members[0])).length();
+    assert!((distance - 20.0).abs() < 1e-3, "members are {} apart", distance);
+    assert!((simulation.velocity(members[0]) - Vec2::new(-10.0, 0.0)).length() < 1e-3);
+    assert!((simulation.velocity(members[1]) - Vec2::new(10.0, 0.0)).length() < 1e-3);
+}
+
+#[test]
+fn collisions_are_sent_once_per_pair() {
+    let mut simulation = collision_simulation();
+    let positions = [
+        Vec2::new(0.0, 0.0),
+        Vec2::new(8.0, 0.0),
+        Vec2::new(4.0, 6.0),
+        Vec2::new(395.0, 100.0),
+        Vec2::new(-395.0, 100.0),
+        Vec2::new(0.0, -200.0)
+    ];
+    let (_, members) = simulation.spawn_flock(&FlockDescription::at(&positions).with_flock(still_flock()));
+
+    simulation.step(1);
+
+    let events = simulation.app.resources.get::<Events<MemberCollision>>().unwrap();
+    let mut pairs: Vec<(usize, usize)> = events.get_reader().iter(&events)
+        .map(|event| {
+            let a = members.iter().position(|x| *x == event.a).unwrap();
+            let b = members.iter().position(|x| *x == event.b).unwrap();
+            (a.min(b), a.max(b))
+        })
+        .collect();
+    pairs.sort();
+
+    assert_eq!(pairs, vec![(0, 1), (0, 2), (1, 2), (3, 4)]);
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics_follow_the_simulation_clock() {
+    use bevy_test::plugins::{ FlockMetricsConfig, FlockMetricsPlugin };
+
+    let path = std::env::temp_dir().join(format!("bevy_test_metrics_{}.csv", std::process::id()));
+    let config = FlockMetricsConfig {
+        path: path.clone(),
+        flush_rows: 1,
+        ..Default::default()
+    };
+
+    let mut simulation = HeadlessSimulation::with_plugins(800.0, 600.0, |app| {
+        app.add_plugin(FlockMetricsPlugin::new(config));
+    });
+    simulation.spawn_flock(&FlockDescription::grid(Vec2::zero(), 4, 3, 30.0));
+    simulation.step(10);
+
+    let mut clock = simulation.clock();
+    clock.paused = true;
+    simulation.set_clock(clock);
+    simulation.step(5);
+
+    let contents = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+    let lines: Vec<&str> = contents.lines().collect();
+
+    assert!(lines[0].starts_with("tick,time,flock,member_count,"));
+    assert_eq!(lines.len(), 11);
+
+    let last: Vec<&str> = lines[10].split(',').collect();
+    assert_eq!(last[0], "9");
+    assert!((last[1].parse::<f64>().unwrap() - 10.0 * HeadlessSimulation::DELTA_SECONDS as f64).abs() < 1e-6);
+    assert_eq!(last[3], "12");
+}